@@ -0,0 +1,62 @@
+use binary_processor::{BatchReader, DecodeStrategy, Schema, DEFAULT_BATCH_SIZE};
+use std::time::{Duration, Instant};
+
+/// Decodes the whole file batch by batch and returns the elapsed time.
+fn run(
+    data: &str,
+    schema: &Schema,
+    strategy: DecodeStrategy,
+    parallel: bool,
+) -> Result<(usize, Duration), binary_processor::Error> {
+    let mut reader = BatchReader::new(data, schema.clone())?;
+    reader.set_decode_strategy(strategy);
+    let start = Instant::now();
+    let mut rows = 0;
+    loop {
+        let batch = if parallel {
+            reader.read_batch_parallel(DEFAULT_BATCH_SIZE * 10)?
+        } else {
+            reader.read_batch(DEFAULT_BATCH_SIZE)?
+        };
+        match batch {
+            Some(batch) => rows += batch.num_rows,
+            None => break,
+        }
+    }
+    Ok((rows, start.elapsed()))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Usage: bench [schema.json] [data.bin]
+    let mut args = std::env::args().skip(1);
+    let schema_path = args
+        .next()
+        .unwrap_or_else(|| ".data/schema.json".to_string());
+    let data_path = args.next().unwrap_or_else(|| ".data/data.bin".to_string());
+
+    let schema: Schema = serde_json::from_str(&std::fs::read_to_string(&schema_path)?)?;
+    println!(
+        "Benchmarking {} ({} channels, {} bytes per row)",
+        data_path,
+        schema.channels.len(),
+        schema.row_size()
+    );
+
+    // RowMajor is the row-by-row RowDecoder path, not the per-row Cursor
+    // decoder BatchReader used before decode strategies existed; time an
+    // older build on the same data to compare against that decoder.
+    for parallel in [false, true] {
+        for strategy in [DecodeStrategy::RowMajor, DecodeStrategy::ColumnMajor] {
+            let (rows, elapsed) = run(&data_path, &schema, strategy, parallel)?;
+            println!(
+                "{:<11} {:<12} {} rows in {:?} ({:.1} rows/ms)",
+                if parallel { "parallel" } else { "sequential" },
+                format!("{:?}", strategy),
+                rows,
+                elapsed,
+                rows as f64 / elapsed.as_secs_f64() / 1000.0
+            );
+        }
+    }
+    Ok(())
+}
@@ -1,68 +1,130 @@
-use binary_processor::{Channel, DataType, Schema};
-use byteorder::{LittleEndian, WriteBytesExt};
+use binary_processor::{
+    Channel, DataType, Endianness, Header, RecordingMetadata, RowWriter, Schema, Timestamp, Value,
+};
 use rand::Rng;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::BufWriter;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// A random value for a channel, or `None` for padding.
+fn random_value<R: Rng>(data_type: DataType, rng: &mut R) -> Option<Value> {
+    Some(match data_type {
+        DataType::Bit => Value::Bit(rng.gen_range(0..=1)),
+        DataType::Int => Value::Int(rng.gen()),
+        DataType::Float => Value::Float(rng.gen()),
+        DataType::I8 => Value::I8(rng.gen()),
+        DataType::I16 => Value::I16(rng.gen()),
+        DataType::I64 => Value::I64(rng.gen()),
+        DataType::U16 => Value::U16(rng.gen()),
+        DataType::U32 => Value::U32(rng.gen()),
+        DataType::U64 => Value::U64(rng.gen()),
+        DataType::F32 => Value::F32(rng.gen()),
+        DataType::Padding(_) => return None,
+    })
+}
+
 fn main() -> std::io::Result<()> {
     let parent_dir = ".data";
+    let byte_order = if std::env::args().any(|a| a == "--big-endian") {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    };
+    // `--rows N` overrides the default of one million rows
+    let args: Vec<String> = std::env::args().collect();
+    // `--container` writes data.bin with an embedded schema header
+    let container = args.iter().any(|a| a == "--container");
+    let num_rows = match args.iter().position(|a| a == "--rows") {
+        Some(i) => args
+            .get(i + 1)
+            .and_then(|n| n.parse::<usize>().ok())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "--rows needs a number")
+            })?,
+        None => 1_000_000,
+    };
     std::fs::create_dir_all(parent_dir)?;
 
     // Generate Schema with 1000 channels
     let mut channels = Vec::new();
     for i in 0..1000 {
-        let data_type = match i % 3 {
+        let data_type = match i % 10 {
             0 => DataType::Bit,
             1 => DataType::Int,
-            _ => DataType::Float,
+            2 => DataType::Float,
+            3 => DataType::I8,
+            4 => DataType::I16,
+            5 => DataType::I64,
+            6 => DataType::U16,
+            7 => DataType::U32,
+            8 => DataType::U64,
+            _ => DataType::F32,
         };
-        channels.push(Channel {
-            name: format!("ch_{}", i),
-            data_type,
-        });
+        channels.push(Channel::new(format!("ch_{}", i), data_type));
+    }
+    let schema = Schema {
+        channels,
+        // Milliseconds since the Unix epoch as f64
+        timestamp: Some(Timestamp::default()),
+        byte_order,
+        row_size: None,
+    };
+
+    let problems = schema.validate();
+    if !problems.is_empty() {
+        let msg = problems
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg));
     }
-    let schema = Schema { channels };
 
     // Save schema
     let schema_json = serde_json::to_string_pretty(&schema)?;
     std::fs::write(format!("{}/schema.json", parent_dir), schema_json)?;
     println!("Generated schema.json with 1000 channels");
 
-    let file = File::create(format!("{}/data.bin", parent_dir))?;
-    let mut writer = BufWriter::new(file);
+    let file = BufWriter::new(File::create(format!("{}/data.bin", parent_dir))?);
+    let mut writer = if container {
+        // Embed the schema so data.bin can be read without schema.json
+        let metadata = RecordingMetadata {
+            created: Some(chrono::Utc::now()),
+            source: Some("generator".to_string()),
+            ..Default::default()
+        };
+        RowWriter::with_header(file, &Header::new(schema.clone(), metadata))
+    } else {
+        RowWriter::new(file, schema.clone())
+    }
+    .map_err(std::io::Error::other)?;
     let mut rng = rand::thread_rng();
 
+    // Timestamps are written in whole milliseconds, passed as microseconds
     let start_time = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_millis() as f64;
-
-    println!("Generating 1,000,000 rows with 1000 channels...");
+        .as_millis() as i64
+        * 1000;
 
-    for i in 0..1_000_000 {
-        // Write timestamp (8 bytes)
-        writer.write_f64::<LittleEndian>(start_time + i as f64)?;
+    println!("Generating {} rows with 1000 channels...", num_rows);
 
-        for channel in &schema.channels {
-            match channel.data_type {
-                DataType::Bit => {
-                    let val: u8 = rng.gen_range(0..=1);
-                    writer.write_u8(val)?;
-                }
-                DataType::Int => {
-                    let val: i32 = rng.gen();
-                    writer.write_i32::<LittleEndian>(val)?;
-                }
-                DataType::Float => {
-                    let val: f64 = rng.gen();
-                    writer.write_f64::<LittleEndian>(val)?;
-                }
-            }
-        }
+    let mut values = Vec::with_capacity(schema.channels.len());
+    for i in 0..num_rows {
+        let ts = start_time + i as i64 * 1000;
+        values.clear();
+        values.extend(
+            schema
+                .channels
+                .iter()
+                .filter_map(|channel| random_value(channel.data_type, &mut rng)),
+        );
+        writer
+            .write_row(Some(ts), &values)
+            .map_err(std::io::Error::other)?;
     }
 
-    writer.flush()?;
+    writer.into_inner().map_err(std::io::Error::other)?;
     println!("Done! Generated data.bin");
     Ok(())
 }
@@ -0,0 +1,32 @@
+use binary_processor::Schema;
+use std::env;
+
+fn main() -> std::io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 || args[1] != "check" {
+        eprintln!("Usage: {} check <schema.json>", args[0]);
+        std::process::exit(2);
+    }
+    let schema_path = &args[2];
+
+    let schema_content = std::fs::read_to_string(schema_path)?;
+    let schema: Schema = serde_json::from_str(&schema_content)?;
+
+    let problems = schema.validate();
+    if problems.is_empty() {
+        println!(
+            "{}: OK ({} channels, {} columns, {} bytes per row)",
+            schema_path,
+            schema.channels.len(),
+            schema.columns().len(),
+            schema.row_size()
+        );
+        return Ok(());
+    }
+
+    println!("{}: {} problem(s)", schema_path, problems.len());
+    for problem in &problems {
+        println!("  - {}", problem);
+    }
+    std::process::exit(1);
+}
@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Compression applied to a recording as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+impl Compression {
+    /// Identifies the format from the first bytes of a stream.
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        match bytes {
+            // ID1, ID2 and the deflate method byte
+            [0x1f, 0x8b, 0x08, ..] => Compression::Gzip,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Compression::Zstd,
+            [0x04, 0x22, 0x4d, 0x18, ..] => Compression::Lz4,
+            _ => Compression::None,
+        }
+    }
+
+    /// Identifies the format from a `.gz`, `.zst`/`.zstd` or `.lz4` extension.
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            Some("lz4") => Compression::Lz4,
+            _ => Compression::None,
+        }
+    }
+
+    /// Uses the extension when it names a compression format and falls back to
+    /// the magic bytes otherwise.
+    pub fn detect(path: &Path, bytes: &[u8]) -> Self {
+        match Self::from_extension(path) {
+            Compression::None => Self::from_magic(bytes),
+            compression => compression,
+        }
+    }
+
+    /// Opens `path` and returns a reader over its contents decompressed as
+    /// `self`, regardless of the extension and first bytes.
+    pub fn open(self, path: impl AsRef<Path>) -> std::io::Result<Box<dyn Read + Send>> {
+        self.decoder(BufReader::new(File::open(path)?))
+    }
+
+    /// Wraps `reader` so that it yields decompressed bytes.
+    pub fn decoder<'a, R: Read + Send + 'a>(
+        self,
+        reader: R,
+    ) -> std::io::Result<Box<dyn Read + Send + 'a>> {
+        Ok(match self {
+            Compression::None => Box::new(reader),
+            Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+            Compression::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(reader)),
+        })
+    }
+}
+
+/// Opens `path` and returns a reader over its decompressed contents, along
+/// with the compression that was detected.
+pub fn open_decompressed(
+    path: impl AsRef<Path>,
+) -> std::io::Result<(Box<dyn Read + Send>, Compression)> {
+    let path = path.as_ref();
+    let mut file = BufReader::new(File::open(path)?);
+    let compression = Compression::detect(path, file.fill_buf()?);
+    Ok((compression.decoder(file)?, compression))
+}
+
+/// Returns a reader over the decompressed contents of `reader`, detecting the
+/// compression from its first bytes.
+pub fn decompress<'a, R: BufRead + Send + 'a>(
+    mut reader: R,
+) -> std::io::Result<(Box<dyn Read + Send + 'a>, Compression)> {
+    let compression = Compression::from_magic(reader.fill_buf()?);
+    Ok((compression.decoder(reader)?, compression))
+}
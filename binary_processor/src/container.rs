@@ -0,0 +1,151 @@
+use crate::{open_decompressed, Error, Schema};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Read, Write};
+
+/// First bytes of a container file.
+pub const MAGIC: [u8; 8] = *b"\x89BINREC\n";
+
+/// Container format version written by `Header::write_to`.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Largest header payload in bytes. Longer lengths are taken as corruption
+/// rather than allocated.
+pub const MAX_HEADER_LEN: u32 = 16 * 1024 * 1024;
+
+/// Information about a recording that is not needed to decode it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RecordingMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    /// Logger, device or program that produced the recording.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Free-form key/value pairs, e.g. test bench or operator.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// Header of the optional container format, placed in front of the rows so
+/// that a recording carries its own schema.
+///
+/// Layout, integers little-endian:
+///
+/// | bytes | content                                      |
+/// |-------|----------------------------------------------|
+/// | 8     | `MAGIC`                                      |
+/// | 2     | format version                               |
+/// | 4     | payload length `n`                           |
+/// | n     | JSON object with `schema` and `metadata`     |
+/// | 4     | CRC-32 of all preceding header bytes         |
+///
+/// Rows follow immediately after the checksum.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Header {
+    pub schema: Schema,
+    #[serde(default)]
+    pub metadata: RecordingMetadata,
+}
+
+impl Header {
+    pub fn new(schema: Schema, metadata: RecordingMetadata) -> Self {
+        Self { schema, metadata }
+    }
+
+    /// Writes the header; the rows are expected to follow.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let payload = serde_json::to_vec(self)
+            .map_err(|e| Error::InvalidHeader(format!("cannot serialize header: {}", e)))?;
+        if payload.len() > MAX_HEADER_LEN as usize {
+            return Err(Error::InvalidHeader(format!(
+                "header payload of {} bytes exceeds {} bytes",
+                payload.len(),
+                MAX_HEADER_LEN
+            )));
+        }
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 10 + payload.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.write_u16::<LittleEndian>(FORMAT_VERSION)?;
+        bytes.write_u32::<LittleEndian>(payload.len() as u32)?;
+        bytes.extend_from_slice(&payload);
+        let checksum = crc32fast::hash(&bytes);
+        bytes.write_u32::<LittleEndian>(checksum)?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Reads a header, including the magic bytes. A reader that runs out of
+    /// bytes early gives an `UnexpectedEof` I/O error; a payload length over
+    /// `MAX_HEADER_LEN` gives `InvalidHeader`.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_counted(reader).map(|(header, _)| header)
+    }
+
+    /// Same as `read_from`, also returning the number of bytes read.
+    pub(crate) fn read_counted<R: Read>(reader: &mut R) -> Result<(Self, usize), Error> {
+        let mut magic = [0u8; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(Error::InvalidHeader("missing magic bytes".to_string()));
+        }
+
+        let version = reader.read_u16::<LittleEndian>()?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(Error::InvalidHeader(format!(
+                "unsupported format version {}",
+                version
+            )));
+        }
+        let len = reader.read_u32::<LittleEndian>()?;
+        if len > MAX_HEADER_LEN {
+            return Err(Error::InvalidHeader(format!(
+                "header payload of {} bytes exceeds {} bytes",
+                len, MAX_HEADER_LEN
+            )));
+        }
+        let mut payload = vec![0u8; len as usize];
+        reader.read_exact(&mut payload)?;
+        let expected = reader.read_u32::<LittleEndian>()?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&magic);
+        hasher.update(&version.to_le_bytes());
+        hasher.update(&len.to_le_bytes());
+        hasher.update(&payload);
+        let actual = hasher.finalize();
+        if actual != expected {
+            return Err(Error::HeaderChecksum { expected, actual });
+        }
+
+        let header = serde_json::from_slice(&payload)
+            .map_err(|e| Error::InvalidHeader(format!("invalid header payload: {}", e)))?;
+        Ok((header, MAGIC.len() + 2 + 4 + payload.len() + 4))
+    }
+
+    /// Reads the header at the start of the file at `path`, decompressing it
+    /// if needed. Returns `None` for a headerless recording.
+    pub fn read_file(path: &str) -> Result<Option<Self>, Error> {
+        let (mut reader, _) = open_decompressed(path)?;
+        let mut magic = [0u8; MAGIC.len()];
+        match reader.read_exact(&mut magic) {
+            Ok(()) if magic == MAGIC => {}
+            Ok(()) => return Ok(None),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        Self::read_from(&mut magic.chain(reader)).map(Some)
+    }
+}
+
+/// Parses the header at the start of `bytes`, returning it with the offset of
+/// the first row, or `None` if `bytes` does not start with `MAGIC`.
+pub(crate) fn parse_header(bytes: &[u8]) -> Result<Option<(Header, usize)>, Error> {
+    if !bytes.starts_with(&MAGIC) {
+        return Ok(None);
+    }
+    Header::read_counted(&mut &bytes[..]).map(Some)
+}
@@ -0,0 +1,374 @@
+use crate::{Batch, ChannelData, Column, DataType, DecodeStrategy, Endianness, Error, Schema};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::io::Read;
+
+/// Reads a value of `data_type` as a raw bit pattern, zero-extended to 64 bits.
+pub(crate) fn read_word<R: Read>(
+    reader: &mut R,
+    data_type: DataType,
+    order: Endianness,
+) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    let bytes = &mut buf[..data_type.size().min(8)];
+    reader.read_exact(bytes)?;
+    Ok(match order {
+        Endianness::Little => LittleEndian::read_uint(bytes, bytes.len()),
+        Endianness::Big => BigEndian::read_uint(bytes, bytes.len()),
+    })
+}
+
+/// Bytes of rows the column-major decoder gathers from at a time.
+const GATHER_TILE_BYTES: usize = 256 * 1024;
+
+/// Turns blocks of whole rows into `Batch`es. The readers own one of these
+/// and differ only in where the row bytes come from. Public only so that the
+/// sealed traits behind `DecodeOptions` can name it; it cannot be reached
+/// from outside the crate.
+pub struct RowDecoder {
+    pub(crate) schema: Schema,
+    layouts: Vec<ColumnLayout>,
+    /// Indices into `schema.columns()` of the columns returned in a batch.
+    selection: Vec<usize>,
+    pub(crate) row_size: usize,
+    pub(crate) engineering_values: bool,
+    pub(crate) strategy: DecodeStrategy,
+}
+
+impl RowDecoder {
+    pub(crate) fn new(schema: Schema) -> Result<Self, Error> {
+        let problems = schema.validate();
+        if !problems.is_empty() {
+            return Err(Error::InvalidSchema(problems));
+        }
+        let layouts = ColumnLayout::for_schema(&schema);
+        Ok(Self {
+            selection: (0..layouts.len()).collect(),
+            layouts,
+            row_size: schema.row_size(),
+            schema,
+            engineering_values: false,
+            strategy: DecodeStrategy::default(),
+        })
+    }
+
+    pub(crate) fn columns(&self) -> Vec<Column<'_>> {
+        let all = self.schema.columns();
+        self.selection.iter().map(|&idx| all[idx]).collect()
+    }
+
+    pub(crate) fn select_columns(&mut self, indices: &[usize]) -> Result<(), Error> {
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.layouts.len()) {
+            return Err(Error::ColumnOutOfRange {
+                index,
+                columns: self.layouts.len(),
+            });
+        }
+        self.selection = indices.to_vec();
+        Ok(())
+    }
+
+    pub(crate) fn select_columns_by_name<S: AsRef<str>>(
+        &mut self,
+        names: &[S],
+    ) -> Result<(), Error> {
+        let all = self.schema.columns();
+        let mut indices = Vec::with_capacity(names.len());
+        for name in names {
+            let name = name.as_ref();
+            match all.iter().position(|c| c.name == name) {
+                Some(idx) => indices.push(idx),
+                None => return Err(Error::UnknownColumn(name.to_string())),
+            }
+        }
+        self.selection = indices;
+        Ok(())
+    }
+
+    /// A batch with no rows and the column types `decode` returns, sized for
+    /// `capacity` rows.
+    pub(crate) fn empty_batch(&self, start_row: usize, capacity: usize) -> Batch {
+        Batch {
+            start_row,
+            num_rows: 0,
+            timestamps: Vec::with_capacity(self.schema.timestamp.as_ref().map_or(0, |_| capacity)),
+            columns: self
+                .selection
+                .iter()
+                .map(|&idx| {
+                    let layout = &self.layouts[idx];
+                    match (self.engineering_values, layout.bit, layout.scaling) {
+                        (true, None, Some(_)) => ChannelData::Float(Vec::with_capacity(capacity)),
+                        _ => layout.new_data(capacity),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Decodes `block`, a run of whole rows. `start_row` and `offset` locate
+    /// the block in its source and are used for the batch and for errors.
+    pub(crate) fn decode(
+        &self,
+        block: &[u8],
+        start_row: usize,
+        offset: usize,
+    ) -> Result<Batch, Error> {
+        let rows_to_read = block.len().checked_div(self.row_size).unwrap_or(0);
+        if rows_to_read == 0 {
+            return Ok(self.empty_batch(start_row, 0));
+        }
+        let block = &block[..rows_to_read * self.row_size];
+
+        // Initialize vectors for this batch
+        let mut batch_results: Vec<ChannelData> = self
+            .selection
+            .iter()
+            .map(|&idx| self.layouts[idx].new_data(rows_to_read))
+            .collect();
+        let timestamp = self.schema.timestamp.as_ref();
+        let mut timestamps = Vec::with_capacity(timestamp.map_or(0, |_| rows_to_read));
+
+        match self.strategy {
+            DecodeStrategy::ColumnMajor => self.gather_rows(
+                block,
+                start_row,
+                offset,
+                &mut timestamps,
+                &mut batch_results,
+            )?,
+            DecodeStrategy::RowMajor => self.walk_rows(
+                block,
+                start_row,
+                offset,
+                &mut timestamps,
+                &mut batch_results,
+            )?,
+        }
+
+        if self.engineering_values {
+            for (data, &col) in batch_results.iter_mut().zip(&self.selection) {
+                let layout = &self.layouts[col];
+                if let (None, Some((scale, offset))) = (layout.bit, layout.scaling) {
+                    if let Some(mut values) = data.to_f64() {
+                        values.iter_mut().for_each(|v| *v = *v * scale + offset);
+                        *data = ChannelData::Float(values);
+                    }
+                }
+            }
+        }
+
+        Ok(Batch {
+            start_row,
+            num_rows: rows_to_read,
+            timestamps,
+            columns: batch_results,
+        })
+    }
+
+    /// Column-major decode: each selected column is gathered over the whole
+    /// block of rows with a fixed stride, using offsets precomputed from the
+    /// schema.
+    fn gather_rows(
+        &self,
+        block: &[u8],
+        start_row: usize,
+        offset: usize,
+        timestamps: &mut Vec<i64>,
+        columns: &mut [ChannelData],
+    ) -> Result<(), Error> {
+        if let Some(timestamp) = &self.schema.timestamp {
+            for (idx, mut row_bytes) in block.chunks_exact(self.row_size).enumerate() {
+                let ts = timestamp
+                    .read(&mut row_bytes, self.schema.byte_order)
+                    .map_err(|_| Error::TruncatedRow {
+                        row: start_row + idx,
+                        offset: offset + idx * self.row_size,
+                    })?;
+                timestamps.push(ts);
+            }
+        }
+
+        // Work through the block in tiles that stay cache-resident while
+        // every selected column is gathered from them.
+        let tile_rows = (GATHER_TILE_BYTES / self.row_size).max(1);
+        for tile in block.chunks(tile_rows * self.row_size) {
+            for (data, &col) in columns.iter_mut().zip(&self.selection) {
+                self.layouts[col].gather(tile, self.row_size, data);
+            }
+        }
+        Ok(())
+    }
+
+    /// Row-major decode: walks the rows one at a time, reading every selected
+    /// value of a row before moving to the next.
+    fn walk_rows(
+        &self,
+        block: &[u8],
+        start_row: usize,
+        offset: usize,
+        timestamps: &mut Vec<i64>,
+        columns: &mut [ChannelData],
+    ) -> Result<(), Error> {
+        for (idx, row_bytes) in block.chunks_exact(self.row_size).enumerate() {
+            let row = start_row + idx;
+            let row_start = offset + idx * self.row_size;
+
+            if let Some(timestamp) = &self.schema.timestamp {
+                let ts = timestamp
+                    .read(&mut &row_bytes[..], self.schema.byte_order)
+                    .map_err(|_| Error::TruncatedRow {
+                        row,
+                        offset: row_start,
+                    })?;
+                timestamps.push(ts);
+            }
+
+            for (data, &col) in columns.iter_mut().zip(&self.selection) {
+                let layout = &self.layouts[col];
+                layout
+                    .decode(row_bytes, data)
+                    .map_err(|_| Error::TruncatedRow {
+                        row,
+                        offset: row_start + layout.offset,
+                    })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where and how one output column is stored within a row.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ColumnLayout {
+    pub(crate) offset: usize,
+    pub(crate) data_type: DataType,
+    pub(crate) order: Endianness,
+    pub(crate) bit: Option<u8>,
+    pub(crate) scaling: Option<(f64, f64)>,
+}
+
+impl ColumnLayout {
+    /// Layouts for `schema.columns()`, in the same order.
+    pub(crate) fn for_schema(schema: &Schema) -> Vec<Self> {
+        let offsets = schema.channel_offsets();
+        schema
+            .columns()
+            .iter()
+            .map(|column| {
+                let channel = &schema.channels[column.channel];
+                ColumnLayout {
+                    offset: offsets[column.channel],
+                    data_type: channel.data_type,
+                    order: schema.byte_order_of(channel),
+                    bit: column.bit,
+                    scaling: channel.scaling(),
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn new_data(&self, capacity: usize) -> ChannelData {
+        match self.bit {
+            Some(_) => ChannelData::Bool(Vec::with_capacity(capacity)),
+            None => ChannelData::with_capacity(self.data_type, capacity),
+        }
+    }
+
+    /// Decodes this column from `row` and appends it to `data`.
+    pub(crate) fn decode(&self, row: &[u8], data: &mut ChannelData) -> std::io::Result<()> {
+        let mut bytes = row.get(self.offset..).unwrap_or_default();
+        match (self.bit, data) {
+            (Some(bit), ChannelData::Bool(flags)) => {
+                let word = read_word(&mut bytes, self.data_type, self.order)?;
+                flags.push(word.checked_shr(bit as u32).unwrap_or(0) & 1 != 0);
+                Ok(())
+            }
+            (_, data) => data.read_value(&mut bytes, self.order),
+        }
+    }
+
+    /// Appends this column for every row of `block`, a run of whole rows of
+    /// `row_size` bytes. The layout must fit inside a row (see
+    /// `Schema::validate`).
+    pub(crate) fn gather(&self, block: &[u8], row_size: usize, data: &mut ChannelData) {
+        if block.is_empty() {
+            return;
+        }
+        let (offset, order) = (self.offset, self.order);
+        match (self.bit, data) {
+            (Some(bit), ChannelData::Bool(flags)) => {
+                let size = self.data_type.size().min(8);
+                flags.extend(block.chunks_exact(row_size).map(|row| {
+                    let bytes = &row[offset..offset + size];
+                    let word = match order {
+                        Endianness::Little => LittleEndian::read_uint(bytes, size),
+                        Endianness::Big => BigEndian::read_uint(bytes, size),
+                    };
+                    word.checked_shr(bit as u32).unwrap_or(0) & 1 != 0
+                }))
+            }
+            (_, ChannelData::Bit(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::Int(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::Float(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::I8(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::I16(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::I64(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::U16(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::U32(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::U64(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::F32(v)) => gather_into(block, row_size, offset, order, v),
+            (_, ChannelData::Bool(v)) => {
+                v.extend(block.chunks_exact(row_size).map(|row| row[offset] != 0))
+            }
+        }
+    }
+}
+
+/// Primitive that can be decoded from a fixed number of bytes.
+trait FixedWidth: Sized {
+    const SIZE: usize;
+    fn from_le(bytes: &[u8]) -> Self;
+    fn from_be(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_fixed_width {
+    ($($t:ty),*) => {
+        $(
+            impl FixedWidth for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+
+                #[inline(always)]
+                fn from_le(bytes: &[u8]) -> Self {
+                    <$t>::from_le_bytes(bytes.try_into().unwrap())
+                }
+
+                #[inline(always)]
+                fn from_be(bytes: &[u8]) -> Self {
+                    <$t>::from_be_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_width!(u8, i8, i16, u16, i32, u32, f32, i64, u64, f64);
+
+/// Strided copy of one field out of every row of `block`. The byte order is
+/// resolved once per call so the inner loop is a plain load per row, which
+/// the compiler can unroll and vectorise.
+#[inline]
+fn gather_into<T: FixedWidth>(
+    block: &[u8],
+    row_size: usize,
+    offset: usize,
+    order: Endianness,
+    out: &mut Vec<T>,
+) {
+    let fields = block
+        .chunks_exact(row_size)
+        .map(|row| &row[offset..offset + T::SIZE]);
+    match order {
+        Endianness::Little => out.extend(fields.map(T::from_le)),
+        Endianness::Big => out.extend(fields.map(T::from_be)),
+    }
+}
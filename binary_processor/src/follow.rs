@@ -0,0 +1,174 @@
+use crate::container::parse_header;
+use crate::decode::RowDecoder;
+use crate::options::{self, sealed};
+use crate::{Batch, DecodeOptions, Error, Header, Schema, MAGIC};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::ErrorKind;
+
+/// Reads a recording that is still being written. Each `read_new` call
+/// remaps the file if it has grown and returns the complete rows appended
+/// since the previous call; a partially written last row is left for later.
+///
+/// Container files are recognised once their header has been written
+/// completely; its schema must equal the one supplied.
+pub struct FollowReader {
+    file: File,
+    /// `None` while the file is empty, which cannot be mapped.
+    mmap: Option<Mmap>,
+    decoder: RowDecoder,
+    /// Offset of the first row, known once the start of the file shows
+    /// whether there is a container header.
+    data_offset: Option<usize>,
+    header: Option<Header>,
+    /// Complete rows in the file as of the last refresh.
+    total_rows: usize,
+    remainder: usize,
+    current_row: usize,
+}
+
+impl FollowReader {
+    pub fn new(filename: &str, schema: Schema) -> Result<Self, Error> {
+        let mut reader = Self {
+            file: File::open(filename)?,
+            mmap: None,
+            decoder: RowDecoder::new(schema)?,
+            data_offset: None,
+            header: None,
+            total_rows: 0,
+            remainder: 0,
+            current_row: 0,
+        };
+        reader.refresh()?;
+        Ok(reader)
+    }
+
+    /// Remaps the file if its length changed and returns the number of
+    /// complete rows that have not been read yet.
+    pub fn refresh(&mut self) -> Result<usize, Error> {
+        let len = self.file.metadata()?.len() as usize;
+        let mapped = self.mmap.as_ref().map_or(0, |m| m.len());
+        if len != mapped {
+            self.mmap = match len {
+                0 => None,
+                _ => Some(unsafe { Mmap::map(&self.file)? }),
+            };
+        }
+        // The file may have changed again between the stat and the map
+        let bytes = self.mmap.as_deref().unwrap_or_default();
+        let len = bytes.len();
+
+        if self.data_offset.is_none() {
+            match Self::detect_header(bytes)? {
+                Some((header, offset)) => {
+                    if header
+                        .as_ref()
+                        .is_some_and(|h| h.schema != self.decoder.schema)
+                    {
+                        return Err(Error::SchemaMismatch);
+                    }
+                    self.header = header;
+                    self.data_offset = Some(offset);
+                }
+                None => {
+                    self.remainder = len;
+                    return Ok(0);
+                }
+            }
+        }
+        let data_len = len.saturating_sub(self.data_offset.unwrap_or(0));
+
+        let row_size = self.decoder.row_size;
+        let total_rows = data_len.checked_div(row_size).unwrap_or(0);
+        if total_rows < self.current_row {
+            return Err(Error::FileShrank {
+                len,
+                rows_read: self.current_row,
+            });
+        }
+        self.total_rows = total_rows;
+        self.remainder = data_len.checked_rem(row_size).unwrap_or(0);
+        Ok(self.total_rows - self.current_row)
+    }
+
+    /// Looks for a container header at the start of the file. Returns `None`
+    /// while the bytes written so far could still be an incomplete header.
+    fn detect_header(bytes: &[u8]) -> Result<Option<(Option<Header>, usize)>, Error> {
+        if bytes.len() < MAGIC.len() && MAGIC.starts_with(bytes) {
+            return Ok(None);
+        }
+        match parse_header(bytes) {
+            Ok(Some((header, offset))) => Ok(Some((Some(header), offset))),
+            Ok(None) => Ok(Some((None, 0))),
+            Err(Error::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The container header, if the file has one.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Complete rows in the file as of the last refresh.
+    pub fn total_rows(&self) -> usize {
+        self.total_rows
+    }
+
+    /// Bytes of a partially written row at the end of the file as of the
+    /// last refresh.
+    pub fn remainder(&self) -> usize {
+        self.remainder
+    }
+
+    /// Index of the next row `read_new` will return.
+    pub fn position(&self) -> usize {
+        self.current_row
+    }
+
+    /// Skips the rows already in the file so that only rows appended from
+    /// now on are returned.
+    pub fn seek_to_end(&mut self) -> Result<(), Error> {
+        self.refresh()?;
+        self.current_row = self.total_rows;
+        Ok(())
+    }
+
+    options::decode_options_methods!();
+
+    /// Returns up to `max_rows` complete rows that have not been read yet, or
+    /// `None` if no new row has been written. Does not block.
+    pub fn read_new(&mut self, max_rows: usize) -> Result<Option<Batch>, Error> {
+        let available = self.refresh()?;
+        if available == 0 {
+            return Ok(None);
+        }
+
+        let rows = available.min(max_rows.max(1));
+        let row_size = self.decoder.row_size;
+        let offset = self.data_offset.unwrap_or(0) + self.current_row * row_size;
+        let mmap = self
+            .mmap
+            .as_ref()
+            .expect("rows are available, so the file is mapped");
+        let batch = self.decoder.decode(
+            &mmap[offset..offset + rows * row_size],
+            self.current_row,
+            offset,
+        )?;
+        self.current_row += rows;
+        Ok(Some(batch))
+    }
+}
+
+impl sealed::Decoders for FollowReader {
+    fn decoder(&self) -> &RowDecoder {
+        &self.decoder
+    }
+
+    fn decoders_mut(&mut self) -> impl Iterator<Item = &mut RowDecoder> {
+        std::iter::once(&mut self.decoder)
+    }
+}
+
+impl DecodeOptions for FollowReader {}
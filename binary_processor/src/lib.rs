@@ -1,153 +1,1343 @@
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use chrono::{DateTime, Utc};
 use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::File;
-use std::io::Cursor;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::sync::Arc;
+
+mod compression;
+mod container;
+mod decode;
+mod follow;
+mod multi;
+mod options;
+mod parallel;
+mod stream;
+mod writer;
+
+pub use compression::{decompress, open_decompressed, Compression};
+pub use container::{Header, RecordingMetadata, FORMAT_VERSION, MAGIC, MAX_HEADER_LEN};
+use decode::RowDecoder;
+pub use follow::FollowReader;
+pub use multi::{glob_paths, FileBoundary, MultiFileReader};
+use options::sealed;
+pub use options::{BatchIterator, DecodeOptions};
+pub use stream::StreamReader;
+pub use writer::RowWriter;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Underlying I/O failure (opening or mapping the file).
+    Io(std::io::Error),
+    /// A row ran out of bytes while decoding. `offset` is the absolute byte
+    /// position in the file where the failing read started.
+    TruncatedRow { row: usize, offset: usize },
+    /// The file ends with `len` bytes that do not form a complete row.
+    TrailingBytes { offset: usize, len: usize },
+    /// The file cannot hold a single row of the schema.
+    SizeMismatch { file_len: usize, row_size: usize },
+    /// The schema failed `Schema::validate`.
+    InvalidSchema(Vec<SchemaProblem>),
+    /// A projection named a column that is not in the schema.
+    UnknownColumn(String),
+    /// A projection used a column index past the end of the schema.
+    ColumnOutOfRange { index: usize, columns: usize },
+    /// A seek or random read started past the last row.
+    RowOutOfRange { row: usize, total_rows: usize },
+    /// A time-based lookup was made on a schema without a timestamp.
+    NoTimestamp,
+    /// Timestamps decrease around this row, so time lookups are unreliable.
+    NonMonotonicTimestamps { row: usize },
+    /// A followed file became shorter than the rows already read from it.
+    FileShrank { len: usize, rows_read: usize },
+    /// A multi-file recording set was given no files.
+    NoInputFiles,
+    /// An invalid glob pattern for a multi-file recording set.
+    InvalidPattern(String),
+    /// A file of a multi-file recording set starts before the previous one ends.
+    FileOrder {
+        file: String,
+        previous: i64,
+        first: i64,
+    },
+    /// A file of a multi-file recording set is compressed.
+    CompressedSetFile {
+        file: String,
+        compression: Compression,
+    },
+    /// Opening this file of a multi-file recording set failed.
+    InFile { file: String, error: Box<Error> },
+    /// A container header that cannot be parsed.
+    InvalidHeader(String),
+    /// The container header does not match its checksum.
+    HeaderChecksum { expected: u32, actual: u32 },
+    /// The schema embedded in a container differs from the one supplied.
+    SchemaMismatch,
+    /// A container was required but the recording has no header.
+    MissingHeader,
+    /// Rows to write have a different number of columns than the schema.
+    ColumnCount { expected: usize, actual: usize },
+    /// Values to write for this column do not have the column's type.
+    ColumnType { column: String },
+    /// A column to write has a different number of values than the others.
+    ColumnLength {
+        column: String,
+        len: usize,
+        expected: usize,
+    },
+    /// A timestamp to write does not fit the schema's timestamp encoding.
+    TimestampOutOfRange(i64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::TruncatedRow { row, offset } => {
+                write!(f, "truncated row {} at byte offset {}", row, offset)
+            }
+            Error::TrailingBytes { offset, len } => write!(
+                f,
+                "{} trailing bytes at byte offset {} do not form a complete row",
+                len, offset
+            ),
+            Error::SizeMismatch { file_len, row_size } => write!(
+                f,
+                "file length {} cannot hold a row of {} bytes",
+                file_len, row_size
+            ),
+            Error::InvalidSchema(problems) => {
+                write!(f, "invalid schema: ")?;
+                for (i, problem) in problems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", problem)?;
+                }
+                Ok(())
+            }
+            Error::UnknownColumn(name) => write!(f, "no column named '{}'", name),
+            Error::ColumnOutOfRange { index, columns } => write!(
+                f,
+                "column index {} is out of range for {} columns",
+                index, columns
+            ),
+            Error::RowOutOfRange { row, total_rows } => {
+                write!(f, "row {} is out of range for {} rows", row, total_rows)
+            }
+            Error::NoTimestamp => write!(f, "schema has no timestamp field"),
+            Error::NonMonotonicTimestamps { row } => {
+                write!(f, "timestamps are not monotonic around row {}", row)
+            }
+            Error::FileShrank { len, rows_read } => write!(
+                f,
+                "file shrank to {} bytes after {} rows were read",
+                len, rows_read
+            ),
+            Error::NoInputFiles => write!(f, "no input files"),
+            Error::InvalidPattern(e) => write!(f, "invalid file pattern: {}", e),
+            Error::FileOrder {
+                file,
+                previous,
+                first,
+            } => write!(
+                f,
+                "{} starts at timestamp {}, before the previous file ends at {}",
+                file, first, previous
+            ),
+            Error::CompressedSetFile { file, compression } => write!(
+                f,
+                "{} is {:?} compressed; files of a multi-file recording must be uncompressed",
+                file, compression
+            ),
+            Error::InFile { file, error } => write!(f, "{}: {}", file, error),
+            Error::InvalidHeader(reason) => write!(f, "invalid container header: {}", reason),
+            Error::HeaderChecksum { expected, actual } => write!(
+                f,
+                "container header checksum mismatch (expected {:08x}, got {:08x})",
+                expected, actual
+            ),
+            Error::SchemaMismatch => write!(
+                f,
+                "the schema embedded in the recording differs from the supplied schema"
+            ),
+            Error::MissingHeader => write!(f, "recording has no container header"),
+            Error::ColumnCount { expected, actual } => {
+                write!(f, "expected {} columns, got {}", expected, actual)
+            }
+            Error::ColumnType { column } => {
+                write!(
+                    f,
+                    "values for column '{}' do not match its data type",
+                    column
+                )
+            }
+            Error::ColumnLength {
+                column,
+                len,
+                expected,
+            } => write!(
+                f,
+                "column '{}' has {} values, expected {}",
+                column, len, expected
+            ),
+            Error::TimestampOutOfRange(value) => write!(
+                f,
+                "timestamp {} cannot be represented in the schema's timestamp encoding",
+                value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum DataType {
-    Bit,   // 1 byte
-    Int,   // 4 bytes (i32)
+    Bit, // 1 byte
+    #[serde(alias = "i32")]
+    Int, // 4 bytes (i32)
+    #[serde(alias = "f64")]
     Float, // 8 bytes (f64)
+    I8,  // 1 byte
+    I16, // 2 bytes
+    I64, // 8 bytes
+    U16, // 2 bytes
+    U32, // 4 bytes
+    U64, // 8 bytes
+    F32, // 4 bytes
+    /// Reserved or alignment bytes that are skipped when decoding.
+    Padding(usize),
 }
 
 impl DataType {
     pub fn size(&self) -> usize {
         match self {
-            DataType::Bit => 1,
-            DataType::Int => 4,
-            DataType::Float => 8,
+            DataType::Padding(n) => *n,
+            DataType::Bit | DataType::I8 => 1,
+            DataType::I16 | DataType::U16 => 2,
+            DataType::Int | DataType::U32 | DataType::F32 => 4,
+            DataType::Float | DataType::I64 | DataType::U64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+/// Unit of decoded timestamp values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Microsecond,
+    Nanosecond,
+}
+
+/// Encoding of the timestamp field at the start of each row.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "encoding", rename_all = "snake_case")]
+pub enum TimestampEncoding {
+    F64Seconds, // 8 bytes
+    F64Millis,  // 8 bytes
+    U64Nanos,   // 8 bytes
+    I64Nanos,   // 8 bytes
+    I64Micros,  // 8 bytes
+    /// 4-byte counter ticking `tick_rate` times per second.
+    U32Ticks {
+        tick_rate: f64,
+    },
+}
+
+impl TimestampEncoding {
+    pub fn size(&self) -> usize {
+        match self {
+            TimestampEncoding::U32Ticks { .. } => 4,
+            _ => 8,
+        }
+    }
+
+    /// Unit of the values produced by `Timestamp::read`. Float encodings are
+    /// decoded to microseconds so fractional milliseconds survive.
+    pub fn unit(&self) -> TimeUnit {
+        match self {
+            TimestampEncoding::F64Seconds
+            | TimestampEncoding::F64Millis
+            | TimestampEncoding::I64Micros => TimeUnit::Microsecond,
+            TimestampEncoding::U64Nanos
+            | TimestampEncoding::I64Nanos
+            | TimestampEncoding::U32Ticks { .. } => TimeUnit::Nanosecond,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Timestamp {
+    #[serde(flatten)]
+    pub encoding: TimestampEncoding,
+    /// Instant a raw value of zero refers to. Defaults to the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<DateTime<Utc>>,
+    /// Time zone attached to the timestamp column, e.g. "UTC" or "+02:00".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl Default for Timestamp {
+    fn default() -> Self {
+        Self {
+            encoding: TimestampEncoding::F64Millis,
+            epoch: None,
+            timezone: None,
+        }
+    }
+}
+
+impl Timestamp {
+    pub fn size(&self) -> usize {
+        self.encoding.size()
+    }
+
+    pub fn unit(&self) -> TimeUnit {
+        self.encoding.unit()
+    }
+
+    /// Decodes one timestamp as a count of `unit()` since the Unix epoch.
+    pub fn read<R: Read>(&self, reader: &mut R, order: Endianness) -> std::io::Result<i64> {
+        let raw = match order {
+            Endianness::Little => self.read_raw::<LittleEndian, R>(reader)?,
+            Endianness::Big => self.read_raw::<BigEndian, R>(reader)?,
+        };
+        Ok(raw.saturating_add(self.epoch_offset()))
+    }
+
+    /// Encodes `value`, a count of `unit()` since the Unix epoch, so that
+    /// `read` returns it again. Float encodings round-trip exactly for whole
+    /// milliseconds (`f64_millis`) or seconds (`f64_seconds`).
+    pub fn write<W: Write>(
+        &self,
+        writer: &mut W,
+        order: Endianness,
+        value: i64,
+    ) -> Result<(), Error> {
+        let raw = self.to_raw(value)?;
+        match order {
+            Endianness::Little => self.write_raw::<LittleEndian, W>(writer, raw)?,
+            Endianness::Big => self.write_raw::<BigEndian, W>(writer, raw)?,
+        }
+        Ok(())
+    }
+
+    /// `value` relative to `epoch`, or `TimestampOutOfRange` if the encoding
+    /// cannot hold it.
+    pub(crate) fn to_raw(&self, value: i64) -> Result<i64, Error> {
+        let raw = value
+            .checked_sub(self.epoch_offset())
+            .ok_or(Error::TimestampOutOfRange(value))?;
+        let fits = match self.encoding {
+            TimestampEncoding::U64Nanos => raw >= 0,
+            TimestampEncoding::U32Ticks { tick_rate } => {
+                (0.0..=u32::MAX as f64).contains(&(raw as f64 * tick_rate / 1e9).round())
+            }
+            _ => true,
+        };
+        if !fits {
+            return Err(Error::TimestampOutOfRange(value));
+        }
+        Ok(raw)
+    }
+
+    /// `epoch` as a count of `unit()` since the Unix epoch.
+    fn epoch_offset(&self) -> i64 {
+        match (self.epoch, self.unit()) {
+            (None, _) => 0,
+            (Some(e), TimeUnit::Microsecond) => e.timestamp_micros(),
+            // `Schema::validate` reports epochs that do not fit
+            (Some(e), TimeUnit::Nanosecond) => e.timestamp_nanos_opt().unwrap_or(i64::MAX),
+        }
+    }
+
+    fn read_raw<B: ByteOrder, R: Read>(&self, reader: &mut R) -> std::io::Result<i64> {
+        Ok(match self.encoding {
+            TimestampEncoding::F64Seconds => (reader.read_f64::<B>()? * 1e6).round() as i64,
+            TimestampEncoding::F64Millis => (reader.read_f64::<B>()? * 1e3).round() as i64,
+            TimestampEncoding::U64Nanos => reader.read_u64::<B>()? as i64,
+            TimestampEncoding::I64Nanos | TimestampEncoding::I64Micros => reader.read_i64::<B>()?,
+            TimestampEncoding::U32Ticks { tick_rate } => {
+                (reader.read_u32::<B>()? as f64 * 1e9 / tick_rate).round() as i64
+            }
+        })
+    }
+
+    fn write_raw<B: ByteOrder, W: Write>(&self, writer: &mut W, raw: i64) -> std::io::Result<()> {
+        match self.encoding {
+            TimestampEncoding::F64Seconds => writer.write_f64::<B>(raw as f64 / 1e6),
+            TimestampEncoding::F64Millis => writer.write_f64::<B>(raw as f64 / 1e3),
+            TimestampEncoding::U64Nanos => writer.write_u64::<B>(raw as u64),
+            TimestampEncoding::I64Nanos | TimestampEncoding::I64Micros => {
+                writer.write_i64::<B>(raw)
+            }
+            TimestampEncoding::U32Ticks { tick_rate } => {
+                writer.write_u32::<B>((raw as f64 * tick_rate / 1e9).round() as u32)
+            }
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+fn default_timestamp() -> Option<Timestamp> {
+    Some(Timestamp::default())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Channel {
     pub name: String,
     pub data_type: DataType,
+    /// Overrides the schema-wide byte order for this channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_order: Option<Endianness>,
+    /// Position of the channel from the start of the row. Defaults to the end
+    /// of the previous channel (or of the timestamp for the first one).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_offset: Option<usize>,
+    /// Named flags packed into this channel's word. When non-empty the channel
+    /// is decoded into one boolean column per flag instead of its raw value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bits: Vec<BitField>,
+    /// Factor applied to raw values: `physical = raw * scale + offset`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// Offset added after scaling raw values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<f64>,
+    /// Engineering unit of the physical value, e.g. "bar" or "°C".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Channel {
+    /// Creates a channel with default layout and no metadata.
+    pub fn new(name: impl Into<String>, data_type: DataType) -> Self {
+        Self {
+            name: name.into(),
+            data_type,
+            byte_order: None,
+            byte_offset: None,
+            bits: Vec::new(),
+            scale: None,
+            offset: None,
+            unit: None,
+            description: None,
+        }
+    }
+
+    /// `(scale, offset)` if the channel declares a linear conversion.
+    pub fn scaling(&self) -> Option<(f64, f64)> {
+        if self.scale.is_none() && self.offset.is_none() {
+            return None;
+        }
+        Some((self.scale.unwrap_or(1.0), self.offset.unwrap_or(0.0)))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BitField {
+    pub name: String,
+    /// Bit index within the word, 0 being the least significant bit.
+    pub position: u8,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// One decoded output column. Plain channels produce one column each, packed
+/// channels produce one boolean column per named bit.
+#[derive(Debug, Clone, Copy)]
+pub struct Column<'a> {
+    pub name: &'a str,
+    /// Index of the source channel in `Schema::channels`.
+    pub channel: usize,
+    /// Bit position for columns taken from a packed channel.
+    pub bit: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Schema {
     pub channels: Vec<Channel>,
+    /// Leading timestamp field of each row; `null` when rows carry none.
+    #[serde(default = "default_timestamp")]
+    pub timestamp: Option<Timestamp>,
+    /// Byte order of the timestamp and of every channel without an override.
+    #[serde(default)]
+    pub byte_order: Endianness,
+    /// Total row size, for records with trailing padding. Defaults to the end
+    /// of the last field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row_size: Option<usize>,
 }
 
 impl Schema {
+    /// Byte order used to decode `channel`.
+    pub fn byte_order_of(&self, channel: &Channel) -> Endianness {
+        channel.byte_order.unwrap_or(self.byte_order)
+    }
+
+    /// Output columns in decode order, with packed channels expanded.
+    pub fn columns(&self) -> Vec<Column<'_>> {
+        let mut columns = Vec::with_capacity(self.channels.len());
+        for (idx, channel) in self.channels.iter().enumerate() {
+            if let DataType::Padding(_) = channel.data_type {
+                continue;
+            }
+            if channel.bits.is_empty() {
+                columns.push(Column {
+                    name: &channel.name,
+                    channel: idx,
+                    bit: None,
+                });
+            } else {
+                for field in &channel.bits {
+                    columns.push(Column {
+                        name: &field.name,
+                        channel: idx,
+                        bit: Some(field.position),
+                    });
+                }
+            }
+        }
+        columns
+    }
+
+    /// Size in bytes of the leading timestamp field (0 if absent).
+    pub fn timestamp_size(&self) -> usize {
+        self.timestamp.as_ref().map_or(0, |t| t.size())
+    }
+
+    /// Byte offset of every channel from the start of the row. Offsets that
+    /// follow a channel ending past `usize::MAX` saturate; `validate` reports
+    /// such layouts.
+    pub fn channel_offsets(&self) -> Vec<usize> {
+        let mut offsets = Vec::with_capacity(self.channels.len());
+        let mut next = self.timestamp_size();
+        for channel in &self.channels {
+            let offset = channel.byte_offset.unwrap_or(next);
+            offsets.push(offset);
+            next = offset.saturating_add(channel.data_type.size());
+        }
+        offsets
+    }
+
     pub fn row_size(&self) -> usize {
-        // Timestamp (8 bytes) + sum of channel sizes
-        8 + self
-            .channels
+        if let Some(row_size) = self.row_size {
+            return row_size;
+        }
+        // End of the furthest field (timestamp or channel)
+        self.channel_offsets()
             .iter()
-            .map(|c| c.data_type.size())
-            .sum::<usize>()
+            .zip(&self.channels)
+            .map(|(offset, c)| offset.saturating_add(c.data_type.size()))
+            .fold(self.timestamp_size(), usize::max)
+    }
+
+    /// Checks the schema for problems that would otherwise surface later as
+    /// misdecoded rows or conversion failures. Returns an empty list if the
+    /// schema is usable.
+    pub fn validate(&self) -> Vec<SchemaProblem> {
+        let mut problems = Vec::new();
+
+        if self.channels.is_empty() {
+            problems.push(SchemaProblem::NoChannels);
+        } else if self.columns().is_empty() {
+            problems.push(SchemaProblem::NoColumns);
+        }
+        if self.row_size() == 0 {
+            problems.push(SchemaProblem::EmptyRow);
+        }
+        if let Some(Timestamp {
+            encoding: TimestampEncoding::U32Ticks { tick_rate },
+            ..
+        }) = self.timestamp
+        {
+            if !(tick_rate > 0.0 && tick_rate.is_finite()) {
+                problems.push(SchemaProblem::InvalidTickRate { tick_rate });
+            }
+        }
+        if let Some(timestamp) = &self.timestamp {
+            if let (Some(epoch), TimeUnit::Nanosecond) = (timestamp.epoch, timestamp.unit()) {
+                if epoch.timestamp_nanos_opt().is_none() {
+                    problems.push(SchemaProblem::EpochOutOfRange { epoch });
+                }
+            }
+        }
+
+        // Output column names
+        let mut seen = std::collections::HashSet::new();
+        for (idx, channel) in self.channels.iter().enumerate() {
+            if let DataType::Padding(_) = channel.data_type {
+                continue;
+            }
+            if channel.name.is_empty() {
+                problems.push(SchemaProblem::EmptyName { channel: idx });
+            }
+            if !channel.bits.is_empty() {
+                let width = match channel.data_type {
+                    DataType::Bit | DataType::U16 | DataType::U32 | DataType::U64 => {
+                        channel.data_type.size() * 8
+                    }
+                    _ => {
+                        problems.push(SchemaProblem::BitsOnNonUnsigned {
+                            channel: channel.name.clone(),
+                        });
+                        continue;
+                    }
+                };
+                for field in &channel.bits {
+                    if field.position as usize >= width {
+                        problems.push(SchemaProblem::BitOutOfRange {
+                            channel: channel.name.clone(),
+                            name: field.name.clone(),
+                            position: field.position,
+                            width,
+                        });
+                    }
+                }
+            }
+        }
+        for column in self.columns() {
+            if column.name.is_empty() {
+                if column.bit.is_some() {
+                    problems.push(SchemaProblem::EmptyName {
+                        channel: column.channel,
+                    });
+                }
+                continue;
+            }
+            if self.timestamp.is_some() && column.name == "timestamp" {
+                problems.push(SchemaProblem::ReservedName {
+                    name: column.name.to_string(),
+                });
+            } else if !seen.insert(column.name) {
+                problems.push(SchemaProblem::DuplicateName {
+                    name: column.name.to_string(),
+                });
+            }
+        }
+
+        // Byte layout
+        let mut fields: Vec<(usize, usize, &str)> = Vec::with_capacity(self.channels.len() + 1);
+        if self.timestamp.is_some() {
+            fields.push((0, self.timestamp_size(), "timestamp"));
+        }
+        for (idx, (offset, channel)) in self
+            .channel_offsets()
+            .into_iter()
+            .zip(&self.channels)
+            .enumerate()
+        {
+            match offset.checked_add(channel.data_type.size()) {
+                Some(end) => fields.push((offset, end, &channel.name)),
+                None => problems.push(SchemaProblem::OffsetOverflow { channel: idx }),
+            }
+        }
+        fields.sort_by_key(|&(start, end, _)| (start, end));
+
+        let mut furthest: Option<(usize, &str)> = None;
+        for &(start, end, name) in &fields {
+            if let Some((prev_end, prev)) = furthest {
+                if start < prev_end {
+                    problems.push(SchemaProblem::Overlap {
+                        first: prev.to_string(),
+                        second: name.to_string(),
+                    });
+                }
+            }
+            if furthest.is_none_or(|(prev_end, _)| end > prev_end) {
+                furthest = Some((end, name));
+            }
+        }
+        let row_size = self.row_size();
+        if let Some((end, name)) = furthest {
+            if end > row_size {
+                problems.push(SchemaProblem::PastRowEnd {
+                    name: name.to_string(),
+                    end,
+                    row_size,
+                });
+            }
+        }
+
+        problems
     }
 }
 
-#[derive(Debug)]
+/// A single problem reported by `Schema::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaProblem {
+    /// The schema declares no channels.
+    NoChannels,
+    /// Every channel is padding, so rows decode to no columns.
+    NoColumns,
+    /// Rows are zero bytes long.
+    EmptyRow,
+    /// The channel at this index (or one of its bit fields) has an empty name.
+    EmptyName { channel: usize },
+    /// Two output columns share a name.
+    DuplicateName { name: String },
+    /// A column is named `timestamp`, which clashes with the timestamp column.
+    ReservedName { name: String },
+    /// Two fields share bytes in the row.
+    Overlap { first: String, second: String },
+    /// A field extends beyond the declared row size.
+    PastRowEnd {
+        name: String,
+        end: usize,
+        row_size: usize,
+    },
+    /// The channel at this index ends past the largest addressable byte
+    /// offset.
+    OffsetOverflow { channel: usize },
+    /// Bit fields were declared on a channel that is not an unsigned integer.
+    BitsOnNonUnsigned { channel: String },
+    /// A bit position does not fit in the channel's word.
+    BitOutOfRange {
+        channel: String,
+        name: String,
+        position: u8,
+        width: usize,
+    },
+    /// The tick rate of a `u32_ticks` timestamp is not a positive number.
+    InvalidTickRate { tick_rate: f64 },
+    /// The epoch of a nanosecond timestamp cannot be expressed as nanoseconds
+    /// since the Unix epoch in an `i64` (years 1677 to 2262).
+    EpochOutOfRange { epoch: DateTime<Utc> },
+}
+
+impl fmt::Display for SchemaProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaProblem::NoChannels => write!(f, "schema has no channels"),
+            SchemaProblem::NoColumns => write!(f, "schema has no channels that are not padding"),
+            SchemaProblem::EmptyRow => write!(f, "rows are 0 bytes long"),
+            SchemaProblem::EmptyName { channel } => {
+                write!(f, "channel {} has an empty name", channel)
+            }
+            SchemaProblem::DuplicateName { name } => {
+                write!(f, "column name '{}' is used more than once", name)
+            }
+            SchemaProblem::ReservedName { name } => {
+                write!(f, "'{}' is reserved for the timestamp column", name)
+            }
+            SchemaProblem::Overlap { first, second } => {
+                write!(f, "'{}' overlaps '{}'", second, first)
+            }
+            SchemaProblem::PastRowEnd {
+                name,
+                end,
+                row_size,
+            } => write!(
+                f,
+                "'{}' ends at byte {} past the row size {}",
+                name, end, row_size
+            ),
+            SchemaProblem::OffsetOverflow { channel } => write!(
+                f,
+                "channel {} ends past the largest addressable byte offset",
+                channel
+            ),
+            SchemaProblem::BitsOnNonUnsigned { channel } => write!(
+                f,
+                "channel '{}' declares bit fields but is not an unsigned integer",
+                channel
+            ),
+            SchemaProblem::BitOutOfRange {
+                channel,
+                name,
+                position,
+                width,
+            } => write!(
+                f,
+                "bit '{}' at position {} does not fit the {}-bit word of '{}'",
+                name, position, width, channel
+            ),
+            SchemaProblem::InvalidTickRate { tick_rate } => {
+                write!(f, "tick rate {} must be a positive number", tick_rate)
+            }
+            SchemaProblem::EpochOutOfRange { epoch } => write!(
+                f,
+                "epoch {} is outside the range of nanosecond timestamps",
+                epoch
+            ),
+        }
+    }
+}
+
+/// A single value of an output column, e.g. one field of a row passed to
+/// `RowWriter::write_row`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Bit(u8),
+    Int(i32),
+    Float(f64),
+    I8(i8),
+    I16(i16),
+    I64(i64),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    Bool(bool),
+}
+
+#[derive(Debug, PartialEq)]
 pub enum ChannelData {
     Bit(Vec<u8>),
     Int(Vec<i32>),
     Float(Vec<f64>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I64(Vec<i64>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F32(Vec<f32>),
+    Bool(Vec<bool>),
 }
 
 impl ChannelData {
+    /// Creates an empty column of the given type.
+    ///
+    /// # Panics
+    ///
+    /// Panics for `DataType::Padding`, which holds no values.
+    pub fn with_capacity(data_type: DataType, capacity: usize) -> Self {
+        match data_type {
+            DataType::Padding(_) => panic!("Padding channels have no column data"),
+            DataType::Bit => ChannelData::Bit(Vec::with_capacity(capacity)),
+            DataType::Int => ChannelData::Int(Vec::with_capacity(capacity)),
+            DataType::Float => ChannelData::Float(Vec::with_capacity(capacity)),
+            DataType::I8 => ChannelData::I8(Vec::with_capacity(capacity)),
+            DataType::I16 => ChannelData::I16(Vec::with_capacity(capacity)),
+            DataType::I64 => ChannelData::I64(Vec::with_capacity(capacity)),
+            DataType::U16 => ChannelData::U16(Vec::with_capacity(capacity)),
+            DataType::U32 => ChannelData::U32(Vec::with_capacity(capacity)),
+            DataType::U64 => ChannelData::U64(Vec::with_capacity(capacity)),
+            DataType::F32 => ChannelData::F32(Vec::with_capacity(capacity)),
+        }
+    }
+
     pub fn len(&self) -> usize {
         match self {
             ChannelData::Bit(v) => v.len(),
             ChannelData::Int(v) => v.len(),
             ChannelData::Float(v) => v.len(),
+            ChannelData::I8(v) => v.len(),
+            ChannelData::I16(v) => v.len(),
+            ChannelData::I64(v) => v.len(),
+            ChannelData::U16(v) => v.len(),
+            ChannelData::U32(v) => v.len(),
+            ChannelData::U64(v) => v.len(),
+            ChannelData::F32(v) => v.len(),
+            ChannelData::Bool(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<Value> {
+        Some(match self {
+            ChannelData::Bit(v) => Value::Bit(*v.get(index)?),
+            ChannelData::Int(v) => Value::Int(*v.get(index)?),
+            ChannelData::Float(v) => Value::Float(*v.get(index)?),
+            ChannelData::I8(v) => Value::I8(*v.get(index)?),
+            ChannelData::I16(v) => Value::I16(*v.get(index)?),
+            ChannelData::I64(v) => Value::I64(*v.get(index)?),
+            ChannelData::U16(v) => Value::U16(*v.get(index)?),
+            ChannelData::U32(v) => Value::U32(*v.get(index)?),
+            ChannelData::U64(v) => Value::U64(*v.get(index)?),
+            ChannelData::F32(v) => Value::F32(*v.get(index)?),
+            ChannelData::Bool(v) => Value::Bool(*v.get(index)?),
+        })
+    }
+
+    /// Decodes one value from `reader` and appends it to the column.
+    pub fn read_value<R: Read>(
+        &mut self,
+        reader: &mut R,
+        order: Endianness,
+    ) -> std::io::Result<()> {
+        match order {
+            Endianness::Little => self.read_value_as::<LittleEndian, R>(reader),
+            Endianness::Big => self.read_value_as::<BigEndian, R>(reader),
+        }
+    }
+
+    fn read_value_as<B: ByteOrder, R: Read>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        match self {
+            ChannelData::Bit(v) => v.push(reader.read_u8()?),
+            ChannelData::Int(v) => v.push(reader.read_i32::<B>()?),
+            ChannelData::Float(v) => v.push(reader.read_f64::<B>()?),
+            ChannelData::I8(v) => v.push(reader.read_i8()?),
+            ChannelData::I16(v) => v.push(reader.read_i16::<B>()?),
+            ChannelData::I64(v) => v.push(reader.read_i64::<B>()?),
+            ChannelData::U16(v) => v.push(reader.read_u16::<B>()?),
+            ChannelData::U32(v) => v.push(reader.read_u32::<B>()?),
+            ChannelData::U64(v) => v.push(reader.read_u64::<B>()?),
+            ChannelData::F32(v) => v.push(reader.read_f32::<B>()?),
+            ChannelData::Bool(v) => v.push(reader.read_u8()? != 0),
+        }
+        Ok(())
+    }
+
+    /// Converts the values to `f64`. Returns `None` for boolean columns.
+    pub fn to_f64(&self) -> Option<Vec<f64>> {
+        Some(match self {
+            ChannelData::Bit(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::Int(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::Float(v) => v.clone(),
+            ChannelData::I8(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::I16(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::I64(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::U16(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::U32(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::U64(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::F32(v) => v.iter().map(|&x| x as f64).collect(),
+            ChannelData::Bool(_) => return None,
+        })
+    }
+
+    /// Moves all values of `other` to the end of this column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two columns hold different types.
+    pub fn append(&mut self, other: ChannelData) {
+        match (self, other) {
+            (ChannelData::Bit(dest), ChannelData::Bit(src)) => dest.extend(src),
+            (ChannelData::Int(dest), ChannelData::Int(src)) => dest.extend(src),
+            (ChannelData::Float(dest), ChannelData::Float(src)) => dest.extend(src),
+            (ChannelData::I8(dest), ChannelData::I8(src)) => dest.extend(src),
+            (ChannelData::I16(dest), ChannelData::I16(src)) => dest.extend(src),
+            (ChannelData::I64(dest), ChannelData::I64(src)) => dest.extend(src),
+            (ChannelData::U16(dest), ChannelData::U16(src)) => dest.extend(src),
+            (ChannelData::U32(dest), ChannelData::U32(src)) => dest.extend(src),
+            (ChannelData::U64(dest), ChannelData::U64(src)) => dest.extend(src),
+            (ChannelData::F32(dest), ChannelData::F32(src)) => dest.extend(src),
+            (ChannelData::Bool(dest), ChannelData::Bool(src)) => dest.extend(src),
+            _ => panic!("Type mismatch when appending channel data"),
+        }
+    }
+}
+
+/// A block of consecutive rows decoded by `BatchReader`.
+#[derive(Debug, PartialEq)]
+pub struct Batch {
+    /// Index of the first row of the batch in the file.
+    pub start_row: usize,
+    pub num_rows: usize,
+    /// One value per row in the unit of `Timestamp::unit`. Empty if the schema
+    /// has no timestamp.
+    pub timestamps: Vec<i64>,
+    /// One entry per selected column, see `DecodeOptions::columns`.
+    pub columns: Vec<ChannelData>,
+}
+
+/// How `BatchReader` treats bytes after the last complete row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadMode {
+    /// Refuse files whose length is not a multiple of `Schema::row_size`.
+    #[default]
+    Strict,
+    /// Accept such files and ignore the partial tail. The caller can check
+    /// `BatchReader::remainder` to warn about it.
+    Lenient,
+}
+
+/// Order in which `BatchReader` walks the bytes of a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeStrategy {
+    /// One column at a time, reading that column's bytes with a fixed stride
+    /// across all rows of the batch.
+    #[default]
+    ColumnMajor,
+    /// One row at a time, decoding every column of the row before moving on.
+    RowMajor,
+}
+
+/// Bytes backing a `BatchReader`: the mapped file, or its decompressed
+/// contents when the file is compressed.
+enum RowBytes {
+    Mapped(Mmap),
+    Decompressed(Vec<u8>),
+}
+
+impl std::ops::Deref for RowBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            RowBytes::Mapped(mmap) => mmap,
+            RowBytes::Decompressed(bytes) => bytes,
         }
     }
 }
 
 pub struct BatchReader {
-    mmap: Mmap,
-    schema: Schema,
-    row_size: usize,
+    data: RowBytes,
+    /// Byte offset of the first row, after a container header.
+    data_offset: usize,
+    header: Option<Header>,
+    compression: Compression,
+    decoder: RowDecoder,
     total_rows: usize,
+    remainder: usize,
     current_row: usize,
+    /// Rows per batch when used as an `Iterator`.
+    batch_size: usize,
+    /// Dedicated pool for the parallel readers; `None` uses the global pool.
+    pool: Option<Arc<rayon::ThreadPool>>,
 }
 
+/// Rows per batch yielded by the `Iterator` impl unless configured otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+
 impl BatchReader {
-    pub fn new(filename: &str, schema: Schema) -> std::io::Result<Self> {
+    pub fn new(filename: &str, schema: Schema) -> Result<Self, Error> {
+        Self::with_mode(filename, schema, ReadMode::Strict)
+    }
+
+    /// Opens a recording. If the file is a container (see `Header`), its
+    /// embedded schema must equal `schema`, otherwise `SchemaMismatch` is
+    /// returned; headerless files are decoded with `schema` as is.
+    pub fn with_mode(filename: &str, schema: Schema, mode: ReadMode) -> Result<Self, Error> {
+        Self::open_with(filename, Some(schema), mode, None)
+    }
+
+    /// Same as `with_mode`, but reads the file as `compression` instead of
+    /// detecting it, e.g. `Compression::None` for raw rows that happen to
+    /// start with the magic bytes of a compression format.
+    pub fn with_compression(
+        filename: &str,
+        schema: Schema,
+        mode: ReadMode,
+        compression: Compression,
+    ) -> Result<Self, Error> {
+        Self::open_with(filename, Some(schema), mode, Some(compression))
+    }
+
+    /// Opens a container file using the schema embedded in its header.
+    /// Returns `MissingHeader` for a headerless recording.
+    pub fn from_container(filename: &str, mode: ReadMode) -> Result<Self, Error> {
+        Self::open_with(filename, None, mode, None)
+    }
+
+    fn open_with(
+        filename: &str,
+        schema: Option<Schema>,
+        mode: ReadMode,
+        compression: Option<Compression>,
+    ) -> Result<Self, Error> {
         let file = File::open(filename)?;
         let mmap = unsafe { Mmap::map(&file)? };
-        let row_size = schema.row_size();
-        let total_rows = mmap.len() / row_size;
+
+        // Compressed files cannot be mapped row by row, so they are
+        // decompressed into memory instead. Use `StreamReader::open` to
+        // decode them without holding the whole recording.
+        let compression =
+            compression.unwrap_or_else(|| Compression::detect(filename.as_ref(), &mmap));
+        let data = match compression {
+            Compression::None => RowBytes::Mapped(mmap),
+            _ => {
+                let mut bytes = Vec::new();
+                compression.decoder(&mmap[..])?.read_to_end(&mut bytes)?;
+                RowBytes::Decompressed(bytes)
+            }
+        };
+
+        let (header, data_offset) = match container::parse_header(&data)? {
+            Some((header, offset)) => (Some(header), offset),
+            None => (None, 0),
+        };
+        let schema = match (schema, &header) {
+            (Some(schema), Some(header)) if schema != header.schema => {
+                return Err(Error::SchemaMismatch)
+            }
+            (Some(schema), _) => schema,
+            (None, Some(header)) => header.schema.clone(),
+            (None, None) => return Err(Error::MissingHeader),
+        };
+        let decoder = RowDecoder::new(schema)?;
+
+        let row_size = decoder.row_size;
+        let len = data.len() - data_offset;
+        let total_rows = len.checked_div(row_size).unwrap_or(0);
+
+        if total_rows == 0 && len > 0 {
+            return Err(Error::SizeMismatch {
+                file_len: len,
+                row_size,
+            });
+        }
+        let remainder = len.checked_rem(row_size).unwrap_or(0);
+        if remainder != 0 && mode == ReadMode::Strict {
+            return Err(Error::TrailingBytes {
+                offset: data_offset + total_rows * row_size,
+                len: remainder,
+            });
+        }
 
         Ok(Self {
-            mmap,
-            schema,
-            row_size,
+            data,
+            data_offset,
+            header,
+            compression,
+            decoder,
             total_rows,
+            remainder,
             current_row: 0,
+            batch_size: DEFAULT_BATCH_SIZE,
+            pool: None,
         })
     }
 
+    pub fn schema(&self) -> &Schema {
+        &self.decoder.schema
+    }
+
+    /// The container header, if the file has one.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
     pub fn total_rows(&self) -> usize {
         self.total_rows
     }
 
-    pub fn read_batch(&mut self, batch_size: usize) -> Option<Vec<ChannelData>> {
+    /// Compression the file was read with, as detected or given when opened.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Number of bytes after the last complete row (always 0 in strict mode).
+    pub fn remainder(&self) -> usize {
+        self.remainder
+    }
+
+    options::decode_options_methods!();
+    options::batch_iterator_methods!();
+
+    /// Index of the next row `read_batch` will return.
+    pub fn position(&self) -> usize {
+        self.current_row
+    }
+
+    /// Moves the reader to `row`. Seeking to `total_rows()` is allowed and
+    /// makes the next `read_batch` return `None`.
+    pub fn seek(&mut self, row: usize) -> Result<(), Error> {
+        if row > self.total_rows {
+            return Err(Error::RowOutOfRange {
+                row,
+                total_rows: self.total_rows,
+            });
+        }
+        self.current_row = row;
+        Ok(())
+    }
+
+    /// Rewinds the reader to the first row.
+    pub fn reset(&mut self) {
+        self.current_row = 0;
+    }
+
+    pub fn read_batch(&mut self, batch_size: usize) -> Result<Option<Batch>, Error> {
         if self.current_row >= self.total_rows {
-            return None;
+            return Ok(None);
         }
 
         let rows_to_read = std::cmp::min(batch_size, self.total_rows - self.current_row);
-        let start_row = self.current_row;
+        let batch = self.decode_rows(self.current_row, rows_to_read)?;
 
-        let num_channels = self.schema.channels.len();
-        let mut batch_results = Vec::with_capacity(num_channels);
+        self.current_row += rows_to_read;
+        Ok(Some(batch))
+    }
 
-        // Initialize vectors for this batch
-        for channel in &self.schema.channels {
-            match channel.data_type {
-                DataType::Bit => {
-                    batch_results.push(ChannelData::Bit(Vec::with_capacity(rows_to_read)))
-                }
-                DataType::Int => {
-                    batch_results.push(ChannelData::Int(Vec::with_capacity(rows_to_read)))
-                }
-                DataType::Float => {
-                    batch_results.push(ChannelData::Float(Vec::with_capacity(rows_to_read)))
-                }
+    /// Reads up to `count` rows starting at `start` without moving the
+    /// reader. Fewer rows are returned if the range runs past the end.
+    pub fn read_rows(&self, start: usize, count: usize) -> Result<Batch, Error> {
+        if start > self.total_rows {
+            return Err(Error::RowOutOfRange {
+                row: start,
+                total_rows: self.total_rows,
+            });
+        }
+        self.decode_rows(start, std::cmp::min(count, self.total_rows - start))
+    }
+
+    fn decode_rows(&self, start_row: usize, rows_to_read: usize) -> Result<Batch, Error> {
+        let row_size = self.decoder.row_size;
+        let offset = self.data_offset + start_row * row_size;
+        let block = &self.data[offset..offset + rows_to_read * row_size];
+        self.decoder.decode(block, start_row, offset)
+    }
+
+    // Helper to read timestamps if we want them separately. Values are in
+    // the unit given by `Timestamp::unit`; empty if the schema has no timestamp.
+    pub fn read_timestamps(&self, start_row: usize, count: usize) -> Result<Vec<i64>, Error> {
+        let timestamp = match &self.decoder.schema.timestamp {
+            Some(t) => t,
+            None => return Ok(Vec::new()),
+        };
+        let mut timestamps = Vec::with_capacity(count);
+        for i in 0..count {
+            let row = start_row + i;
+            if row >= self.total_rows {
+                break;
             }
+            timestamps.push(self.timestamp_at(timestamp, row)?);
         }
+        Ok(timestamps)
+    }
 
-        for idx in 0..rows_to_read {
-            let row_start = (start_row + idx) * self.row_size;
-            let mut cursor = Cursor::new(&self.mmap[row_start..row_start + self.row_size]);
+    fn timestamp_at(&self, timestamp: &Timestamp, row: usize) -> Result<i64, Error> {
+        let row_size = self.decoder.row_size;
+        let offset = self.data_offset + row * row_size;
+        timestamp
+            .read(
+                &mut &self.data[offset..offset + row_size],
+                self.decoder.schema.byte_order,
+            )
+            .map_err(|_| Error::TruncatedRow { row, offset })
+    }
 
-            // Skip timestamp (8 bytes)
-            let _ = cursor.read_f64::<LittleEndian>().unwrap();
+    /// Index of the first row whose timestamp is at or after `t` (in the unit
+    /// of `Timestamp::unit`), or `total_rows()` if there is none.
+    ///
+    /// Uses a binary search, so timestamps must not decrease. Every probed row
+    /// is checked against its neighbours in the search and a
+    /// `NonMonotonicTimestamps` error is returned if the order is violated.
+    pub fn row_for_time(&self, t: i64) -> Result<usize, Error> {
+        let timestamp = self
+            .decoder
+            .schema
+            .timestamp
+            .as_ref()
+            .ok_or(Error::NoTimestamp)?;
+        if self.total_rows == 0 {
+            return Ok(0);
+        }
 
-            for i in 0..num_channels {
-                match &mut batch_results[i] {
-                    ChannelData::Bit(vec) => vec.push(cursor.read_u8().unwrap()),
-                    ChannelData::Int(vec) => vec.push(cursor.read_i32::<LittleEndian>().unwrap()),
-                    ChannelData::Float(vec) => vec.push(cursor.read_f64::<LittleEndian>().unwrap()),
-                }
+        // Invariant: rows before `lo` are < t, rows at or after `hi` are >= t.
+        // `lo_ts`/`hi_ts` are the timestamps bounding the open interval.
+        let mut lo = 0;
+        let mut hi = self.total_rows;
+        let mut lo_ts = i64::MIN;
+        let mut hi_ts = i64::MAX;
+        let first = self.timestamp_at(timestamp, 0)?;
+        let last = self.timestamp_at(timestamp, self.total_rows - 1)?;
+        if first > last {
+            return Err(Error::NonMonotonicTimestamps {
+                row: self.total_rows - 1,
+            });
+        }
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let ts = self.timestamp_at(timestamp, mid)?;
+            if ts < lo_ts || ts > hi_ts || ts < first || ts > last {
+                return Err(Error::NonMonotonicTimestamps { row: mid });
+            }
+            if ts < t {
+                lo = mid + 1;
+                lo_ts = ts;
+            } else {
+                hi = mid;
+                hi_ts = ts;
             }
         }
+        Ok(lo)
+    }
 
-        self.current_row += rows_to_read;
-        Some(batch_results)
+    /// Rows whose timestamps fall in `[t0, t1)`.
+    pub fn time_range_rows(&self, t0: i64, t1: i64) -> Result<Range<usize>, Error> {
+        let start = self.row_for_time(t0)?;
+        let end = self.row_for_time(t1)?.max(start);
+        Ok(start..end)
     }
 
-    // Helper to read timestamps if we want them separately
-    pub fn read_timestamps(&self, start_row: usize, count: usize) -> Vec<f64> {
-        let mut timestamps = Vec::with_capacity(count);
-        for i in 0..count {
-            if start_row + i >= self.total_rows {
-                break;
-            }
-            let offset = (start_row + i) * self.row_size;
-            let ts = (&self.mmap[offset..offset + 8])
-                .read_f64::<LittleEndian>()
-                .unwrap();
-            timestamps.push(ts);
+    /// Reads the rows whose timestamps fall in `[t0, t1)` without moving the
+    /// reader.
+    pub fn read_time_range(&self, t0: i64, t1: i64) -> Result<Batch, Error> {
+        let rows = self.time_range_rows(t0, t1)?;
+        self.read_rows(rows.start, rows.len())
+    }
+}
+
+/// Yields consecutive batches of `batch_size()` rows from the current
+/// position. After an error the iterator is exhausted.
+impl Iterator for BatchReader {
+    type Item = Result<Batch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.read_batch(self.batch_size).transpose();
+        if let Some(Err(_)) = result {
+            self.current_row = self.total_rows;
         }
-        timestamps
+        result
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.total_rows - self.current_row).div_ceil(self.batch_size);
+        (remaining, Some(remaining))
+    }
+
+    /// Skips `n` batches without decoding them.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let skip = n.saturating_mul(self.batch_size);
+        self.current_row = self.current_row.saturating_add(skip).min(self.total_rows);
+        self.next()
+    }
+}
+
+impl sealed::Decoders for BatchReader {
+    fn decoder(&self) -> &RowDecoder {
+        &self.decoder
+    }
+
+    fn decoders_mut(&mut self) -> impl Iterator<Item = &mut RowDecoder> {
+        std::iter::once(&mut self.decoder)
+    }
+}
+
+impl DecodeOptions for BatchReader {}
+
+impl sealed::BatchSize for BatchReader {
+    fn batch_rows(&self) -> usize {
+        self.batch_size
+    }
+
+    fn batch_size_mut(&mut self) -> &mut usize {
+        &mut self.batch_size
     }
 }
+
+impl BatchIterator for BatchReader {}
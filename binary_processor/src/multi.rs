@@ -0,0 +1,366 @@
+use crate::decode::RowDecoder;
+use crate::options::{self, sealed};
+use crate::DEFAULT_BATCH_SIZE;
+use crate::{
+    Batch, BatchIterator, BatchReader, Compression, DecodeOptions, Error, ReadMode, Schema,
+};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Where one file of a recording set ends and the next begins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileBoundary {
+    /// Index of the file that starts at this boundary.
+    pub file: usize,
+    /// Global index of the first row of that file.
+    pub row: usize,
+    /// Last timestamp of the previous file.
+    pub previous: i64,
+    /// First timestamp of this file.
+    pub first: i64,
+    /// Interval between the last two rows of the previous file, if it has
+    /// at least two rows. Useful to judge whether `gap()` is a dropout.
+    pub step: Option<i64>,
+}
+
+impl FileBoundary {
+    /// Time between the last row of the previous file and the first row of
+    /// this one.
+    pub fn gap(&self) -> i64 {
+        self.first - self.previous
+    }
+}
+
+/// Reads a recording split over several files that share one schema, such as
+/// `data_000.bin`, `data_001.bin`, ..., as one continuous sequence of rows.
+///
+/// Row indices in batches and in `seek`/`read_rows` are global across the
+/// set. When the schema has a timestamp, each file must not start before the
+/// previous one ends. Compressed files are rejected, as every file would be
+/// decompressed into memory at once; decode them one by one with
+/// `StreamReader::open` instead.
+pub struct MultiFileReader {
+    files: Vec<BatchReader>,
+    paths: Vec<String>,
+    /// Global index of the first row of each file.
+    starts: Vec<usize>,
+    total_rows: usize,
+    current_row: usize,
+    /// Rows per batch when used as an `Iterator`.
+    batch_size: usize,
+}
+
+impl MultiFileReader {
+    pub fn new<S: AsRef<str>>(paths: &[S], schema: Schema) -> Result<Self, Error> {
+        Self::with_mode(paths, schema, ReadMode::Strict)
+    }
+
+    /// Opens the files in the given order. `mode` applies to every file.
+    pub fn with_mode<S: AsRef<str>>(
+        paths: &[S],
+        schema: Schema,
+        mode: ReadMode,
+    ) -> Result<Self, Error> {
+        Self::open(paths, schema, mode, None)
+    }
+
+    /// Same as `with_mode`, but reads every file as `compression` instead of
+    /// detecting it. Anything but `Compression::None` is rejected with
+    /// `CompressedSetFile`, as for detected compression.
+    pub fn with_compression<S: AsRef<str>>(
+        paths: &[S],
+        schema: Schema,
+        mode: ReadMode,
+        compression: Compression,
+    ) -> Result<Self, Error> {
+        Self::open(paths, schema, mode, Some(compression))
+    }
+
+    fn open<S: AsRef<str>>(
+        paths: &[S],
+        schema: Schema,
+        mode: ReadMode,
+        compression: Option<Compression>,
+    ) -> Result<Self, Error> {
+        if paths.is_empty() {
+            return Err(Error::NoInputFiles);
+        }
+        let mut files = Vec::with_capacity(paths.len());
+        let mut starts = Vec::with_capacity(paths.len());
+        let mut total_rows = 0;
+        for path in paths {
+            let path = path.as_ref();
+            let file = match compression {
+                Some(compression) => {
+                    BatchReader::with_compression(path, schema.clone(), mode, compression)
+                }
+                None => BatchReader::with_mode(path, schema.clone(), mode),
+            }
+            .map_err(|error| Error::InFile {
+                file: path.to_string(),
+                error: Box::new(error),
+            })?;
+            if file.compression() != Compression::None {
+                return Err(Error::CompressedSetFile {
+                    file: path.to_string(),
+                    compression: file.compression(),
+                });
+            }
+            starts.push(total_rows);
+            total_rows += file.total_rows();
+            files.push(file);
+        }
+
+        let reader = Self {
+            files,
+            paths: paths.iter().map(|p| p.as_ref().to_string()).collect(),
+            starts,
+            total_rows,
+            current_row: 0,
+            batch_size: DEFAULT_BATCH_SIZE,
+        };
+        if let Some(boundary) = reader.boundaries()?.iter().find(|b| b.gap() < 0) {
+            return Err(Error::FileOrder {
+                file: reader.paths[boundary.file].clone(),
+                previous: boundary.previous,
+                first: boundary.first,
+            });
+        }
+        Ok(reader)
+    }
+
+    /// Opens every file matching `pattern` (e.g. `data_*.bin`), in
+    /// lexicographic order.
+    pub fn glob(pattern: &str, schema: Schema, mode: ReadMode) -> Result<Self, Error> {
+        let paths = glob_paths(pattern)?;
+        Self::with_mode(&paths, schema, mode)
+    }
+
+    /// Paths of the files in the set, in reading order.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// The reader of each file, in reading order.
+    pub fn files(&self) -> &[BatchReader] {
+        &self.files
+    }
+
+    /// Total rows over all files.
+    pub fn total_rows(&self) -> usize {
+        self.total_rows
+    }
+
+    /// File index and row within that file of the global `row`.
+    pub fn locate(&self, row: usize) -> Result<(usize, usize), Error> {
+        if row >= self.total_rows {
+            return Err(Error::RowOutOfRange {
+                row,
+                total_rows: self.total_rows,
+            });
+        }
+        // Last file starting at or before `row`; empty files are skipped over
+        let file = self.starts.partition_point(|&start| start <= row) - 1;
+        Ok((file, row - self.starts[file]))
+    }
+
+    /// The timestamps on both sides of every boundary between two non-empty
+    /// files. Empty if the schema has no timestamp.
+    pub fn boundaries(&self) -> Result<Vec<FileBoundary>, Error> {
+        let mut boundaries = Vec::new();
+        if self.files[0].decoder.schema.timestamp.is_none() {
+            return Ok(boundaries);
+        }
+        let mut previous: Option<&BatchReader> = None;
+        for (index, file) in self.files.iter().enumerate() {
+            if file.total_rows() == 0 {
+                continue;
+            }
+            if let Some(prev) = previous {
+                let n = prev.total_rows();
+                let tail = prev.read_timestamps(n.saturating_sub(2), 2)?;
+                boundaries.push(FileBoundary {
+                    file: index,
+                    row: self.starts[index],
+                    previous: tail[tail.len() - 1],
+                    first: file.read_timestamps(0, 1)?[0],
+                    step: (tail.len() == 2).then(|| tail[1] - tail[0]),
+                });
+            }
+            previous = Some(file);
+        }
+        Ok(boundaries)
+    }
+
+    options::decode_options_methods!();
+    options::batch_iterator_methods!();
+
+    /// Sets the number of threads used by the parallel readers. `0` uses the
+    /// global rayon pool (one thread per core).
+    pub fn set_threads(&mut self, threads: usize) -> Result<(), Error> {
+        self.files[0].set_threads(threads)?;
+        let pool = self.files[0].pool.clone();
+        for file in &mut self.files[1..] {
+            file.pool = pool.as_ref().map(Arc::clone);
+        }
+        Ok(())
+    }
+
+    /// Global index of the next row `read_batch` will return.
+    pub fn position(&self) -> usize {
+        self.current_row
+    }
+
+    /// Moves the reader to the global `row`. Seeking to `total_rows()` is
+    /// allowed and makes the next `read_batch` return `None`.
+    pub fn seek(&mut self, row: usize) -> Result<(), Error> {
+        if row > self.total_rows {
+            return Err(Error::RowOutOfRange {
+                row,
+                total_rows: self.total_rows,
+            });
+        }
+        self.current_row = row;
+        Ok(())
+    }
+
+    /// Rewinds the reader to the first row of the first file.
+    pub fn reset(&mut self) {
+        self.current_row = 0;
+    }
+
+    pub fn read_batch(&mut self, batch_size: usize) -> Result<Option<Batch>, Error> {
+        self.next_batch(batch_size, false)
+    }
+
+    /// Parallel counterpart of `read_batch`.
+    pub fn read_batch_parallel(&mut self, batch_size: usize) -> Result<Option<Batch>, Error> {
+        self.next_batch(batch_size, true)
+    }
+
+    /// Reads up to `count` rows starting at the global row `start` without
+    /// moving the reader. The rows may span several files.
+    pub fn read_rows(&self, start: usize, count: usize) -> Result<Batch, Error> {
+        self.read_span(start, count, false)
+    }
+
+    /// Parallel counterpart of `read_rows`.
+    pub fn read_rows_parallel(&self, start: usize, count: usize) -> Result<Batch, Error> {
+        self.read_span(start, count, true)
+    }
+
+    fn next_batch(&mut self, batch_size: usize, parallel: bool) -> Result<Option<Batch>, Error> {
+        if self.current_row >= self.total_rows {
+            return Ok(None);
+        }
+        let batch = self.read_span(self.current_row, batch_size, parallel)?;
+        self.current_row += batch.num_rows;
+        Ok(Some(batch))
+    }
+
+    fn read_span(&self, start: usize, count: usize, parallel: bool) -> Result<Batch, Error> {
+        if start > self.total_rows {
+            return Err(Error::RowOutOfRange {
+                row: start,
+                total_rows: self.total_rows,
+            });
+        }
+        let count = count.min(self.total_rows - start);
+        let mut batch = self.files[0].decoder.empty_batch(start, count);
+
+        let mut row = start;
+        while row < start + count {
+            let (index, local) = self.locate(row)?;
+            let file = &self.files[index];
+            let rows = (file.total_rows() - local).min(start + count - row);
+            let part = match parallel {
+                true => file.read_rows_parallel(local, rows)?,
+                false => file.read_rows(local, rows)?,
+            };
+            batch.append(part);
+            row += rows;
+        }
+        Ok(batch)
+    }
+
+    /// Global index of the first row whose timestamp is at or after `t`, or
+    /// `total_rows()` if there is none. See `BatchReader::row_for_time`.
+    pub fn row_for_time(&self, t: i64) -> Result<usize, Error> {
+        for (file, &start) in self.files.iter().zip(&self.starts) {
+            let row = file.row_for_time(t)?;
+            if row < file.total_rows() {
+                return Ok(start + row);
+            }
+        }
+        Ok(self.total_rows)
+    }
+
+    /// Global rows whose timestamps fall in `[t0, t1)`.
+    pub fn time_range_rows(&self, t0: i64, t1: i64) -> Result<Range<usize>, Error> {
+        let start = self.row_for_time(t0)?;
+        let end = self.row_for_time(t1)?.max(start);
+        Ok(start..end)
+    }
+
+    /// Reads the rows whose timestamps fall in `[t0, t1)` without moving the
+    /// reader.
+    pub fn read_time_range(&self, t0: i64, t1: i64) -> Result<Batch, Error> {
+        let rows = self.time_range_rows(t0, t1)?;
+        self.read_rows(rows.start, rows.len())
+    }
+}
+
+/// Yields consecutive batches of `batch_size()` rows from the current
+/// position, crossing file boundaries. After an error the iterator is
+/// exhausted.
+impl Iterator for MultiFileReader {
+    type Item = Result<Batch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.read_batch(self.batch_size).transpose();
+        if let Some(Err(_)) = result {
+            self.current_row = self.total_rows;
+        }
+        result
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.total_rows - self.current_row).div_ceil(self.batch_size);
+        (remaining, Some(remaining))
+    }
+}
+
+impl sealed::Decoders for MultiFileReader {
+    fn decoder(&self) -> &RowDecoder {
+        &self.files[0].decoder
+    }
+
+    fn decoders_mut(&mut self) -> impl Iterator<Item = &mut RowDecoder> {
+        self.files.iter_mut().map(|file| &mut file.decoder)
+    }
+}
+
+impl DecodeOptions for MultiFileReader {}
+
+impl sealed::BatchSize for MultiFileReader {
+    fn batch_rows(&self) -> usize {
+        self.batch_size
+    }
+
+    fn batch_size_mut(&mut self) -> &mut usize {
+        &mut self.batch_size
+    }
+}
+
+impl BatchIterator for MultiFileReader {}
+
+/// Files matching `pattern` (e.g. `data_*.bin`), in lexicographic order.
+pub fn glob_paths(pattern: &str) -> Result<Vec<String>, Error> {
+    let mut paths = Vec::new();
+    for entry in glob::glob(pattern).map_err(|e| Error::InvalidPattern(e.to_string()))? {
+        let path = entry.map_err(|e| Error::Io(std::io::Error::from(e)))?;
+        paths.push(path.to_string_lossy().into_owned());
+    }
+    paths.sort();
+    Ok(paths)
+}
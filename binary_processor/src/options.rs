@@ -0,0 +1,137 @@
+use crate::{Batch, Column, DecodeStrategy, Error};
+
+/// Decoding settings shared by `BatchReader`, `StreamReader`, `FollowReader`
+/// and `MultiFileReader`.
+pub trait DecodeOptions: sealed::Decoders {
+    /// When enabled, channels with a `scale` or `offset` are returned as
+    /// `ChannelData::Float` holding `raw * scale + offset`.
+    fn set_engineering_values(&mut self, enabled: bool) {
+        for decoder in self.decoders_mut() {
+            decoder.engineering_values = enabled;
+        }
+    }
+
+    fn set_decode_strategy(&mut self, strategy: DecodeStrategy) {
+        for decoder in self.decoders_mut() {
+            decoder.strategy = strategy;
+        }
+    }
+
+    /// Columns of the batches the reader returns, in order.
+    fn columns(&self) -> Vec<Column<'_>> {
+        self.decoder().columns()
+    }
+
+    /// Restricts the batches to the given columns, by index into
+    /// `Schema::columns`. Readers that map the file only read the bytes of
+    /// those columns.
+    fn select_columns(&mut self, indices: &[usize]) -> Result<(), Error> {
+        for decoder in self.decoders_mut() {
+            decoder.select_columns(indices)?;
+        }
+        Ok(())
+    }
+
+    /// Same as `select_columns`, using column names.
+    fn select_columns_by_name<S: AsRef<str>>(&mut self, names: &[S]) -> Result<(), Error> {
+        for decoder in self.decoders_mut() {
+            decoder.select_columns_by_name(names)?;
+        }
+        Ok(())
+    }
+}
+
+/// Readers that yield consecutive batches of `batch_size()` rows as an
+/// `Iterator`.
+pub trait BatchIterator: Iterator<Item = Result<Batch, Error>> + sealed::BatchSize {
+    /// Sets the number of rows per batch yielded by the `Iterator` impl.
+    fn set_batch_size(&mut self, batch_size: usize) {
+        *self.batch_size_mut() = batch_size.max(1);
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_rows()
+    }
+
+    /// Turns the reader into an iterator of `batch_size`-row batches.
+    fn into_batches(mut self, batch_size: usize) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_batch_size(batch_size);
+        self
+    }
+}
+
+/// Inherent methods forwarding to `DecodeOptions`, so that existing callers
+/// keep working without importing the trait.
+macro_rules! decode_options_methods {
+    () => {
+        /// Same as `DecodeOptions::set_engineering_values`.
+        pub fn set_engineering_values(&mut self, enabled: bool) {
+            $crate::DecodeOptions::set_engineering_values(self, enabled)
+        }
+
+        /// Same as `DecodeOptions::set_decode_strategy`.
+        pub fn set_decode_strategy(&mut self, strategy: $crate::DecodeStrategy) {
+            $crate::DecodeOptions::set_decode_strategy(self, strategy)
+        }
+
+        /// Same as `DecodeOptions::columns`.
+        pub fn columns(&self) -> Vec<$crate::Column<'_>> {
+            $crate::DecodeOptions::columns(self)
+        }
+
+        /// Same as `DecodeOptions::select_columns`.
+        pub fn select_columns(&mut self, indices: &[usize]) -> Result<(), $crate::Error> {
+            $crate::DecodeOptions::select_columns(self, indices)
+        }
+
+        /// Same as `DecodeOptions::select_columns_by_name`.
+        pub fn select_columns_by_name<S: AsRef<str>>(
+            &mut self,
+            names: &[S],
+        ) -> Result<(), $crate::Error> {
+            $crate::DecodeOptions::select_columns_by_name(self, names)
+        }
+    };
+}
+
+/// Inherent methods forwarding to `BatchIterator`, for the same reason.
+macro_rules! batch_iterator_methods {
+    () => {
+        /// Same as `BatchIterator::set_batch_size`.
+        pub fn set_batch_size(&mut self, batch_size: usize) {
+            $crate::BatchIterator::set_batch_size(self, batch_size)
+        }
+
+        /// Same as `BatchIterator::batch_size`.
+        pub fn batch_size(&self) -> usize {
+            $crate::BatchIterator::batch_size(self)
+        }
+
+        /// Same as `BatchIterator::into_batches`.
+        pub fn into_batches(self, batch_size: usize) -> Self {
+            $crate::BatchIterator::into_batches(self, batch_size)
+        }
+    };
+}
+
+pub(crate) use {batch_iterator_methods, decode_options_methods};
+
+/// Access to reader state that the public traits are implemented on top of.
+pub(crate) mod sealed {
+    use crate::decode::RowDecoder;
+
+    pub trait Decoders {
+        /// The decoder that describes the columns of every batch.
+        fn decoder(&self) -> &RowDecoder;
+        /// Every decoder of the reader; a multi-file reader has one per file.
+        fn decoders_mut(&mut self) -> impl Iterator<Item = &mut RowDecoder>;
+    }
+
+    pub trait BatchSize {
+        fn batch_rows(&self) -> usize;
+        fn batch_size_mut(&mut self) -> &mut usize;
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{Batch, BatchReader, Error};
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// Rows decoded by one parallel task.
+const CHUNK_ROWS: usize = 10_000;
+
+impl Batch {
+    /// Moves the rows of `other` to the end of this batch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two batches hold different column types.
+    pub fn append(&mut self, other: Batch) {
+        self.num_rows += other.num_rows;
+        self.timestamps.extend(other.timestamps);
+        for (dest, src) in self.columns.iter_mut().zip(other.columns) {
+            dest.append(src);
+        }
+    }
+}
+
+impl BatchReader {
+    /// Sets the number of threads used by the parallel readers. `0` uses the
+    /// global rayon pool (one thread per core).
+    pub fn set_threads(&mut self, threads: usize) -> Result<(), Error> {
+        self.pool = match threads {
+            0 => None,
+            n => Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(n)
+                    .build()
+                    .map_err(|e| Error::Io(std::io::Error::other(e)))?,
+            )),
+        };
+        Ok(())
+    }
+
+    /// Parallel counterpart of `read_batch`.
+    pub fn read_batch_parallel(&mut self, batch_size: usize) -> Result<Option<Batch>, Error> {
+        if self.current_row >= self.total_rows {
+            return Ok(None);
+        }
+
+        let rows_to_read = std::cmp::min(batch_size, self.total_rows - self.current_row);
+        let batch = self.read_rows_parallel(self.current_row, rows_to_read)?;
+
+        self.current_row += rows_to_read;
+        Ok(Some(batch))
+    }
+
+    /// Parallel counterpart of `read_rows`: splits the range into chunks that
+    /// are decoded concurrently and merged in order.
+    pub fn read_rows_parallel(&self, start: usize, count: usize) -> Result<Batch, Error> {
+        if start > self.total_rows {
+            return Err(Error::RowOutOfRange {
+                row: start,
+                total_rows: self.total_rows,
+            });
+        }
+        let count = std::cmp::min(count, self.total_rows - start);
+
+        let chunks: Vec<(usize, usize)> = (start..start + count)
+            .step_by(CHUNK_ROWS)
+            .map(|chunk_start| {
+                let rows = std::cmp::min(CHUNK_ROWS, start + count - chunk_start);
+                (chunk_start, rows)
+            })
+            .collect();
+
+        let decode = || {
+            chunks
+                .par_iter()
+                .map(|&(chunk_start, rows)| self.decode_rows(chunk_start, rows))
+                .collect::<Result<Vec<Batch>, Error>>()
+        };
+        let partial_results = match &self.pool {
+            Some(pool) => pool.install(decode)?,
+            None => decode()?,
+        };
+
+        // Merge results
+        let mut batch = self.decoder.empty_batch(start, count);
+        for chunk in partial_results {
+            batch.append(chunk);
+        }
+        Ok(batch)
+    }
+
+    /// Decodes every row of the file in parallel without moving the reader.
+    pub fn read_all_parallel(&self) -> Result<Batch, Error> {
+        self.read_rows_parallel(0, self.total_rows)
+    }
+}
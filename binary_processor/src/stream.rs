@@ -0,0 +1,230 @@
+use crate::decode::RowDecoder;
+use crate::options::{self, sealed};
+use crate::{
+    open_decompressed, Batch, BatchIterator, Compression, DecodeOptions, Error, Header, ReadMode,
+    Schema, DEFAULT_BATCH_SIZE, MAGIC,
+};
+use std::io::Read;
+
+/// Decodes the row format incrementally from any `Read`, such as stdin, a
+/// pipe or a socket. Unlike `BatchReader` it only moves forward and does not
+/// know the number of rows up front.
+pub struct StreamReader<R> {
+    reader: R,
+    decoder: RowDecoder,
+    mode: ReadMode,
+    /// Holds the bytes of the batch being decoded, reused between batches.
+    buffer: Vec<u8>,
+    /// Bytes already read from `reader` that belong to later rows.
+    pending: Vec<u8>,
+    header: Option<Header>,
+    /// Stream offset of the first row, after a container header.
+    data_offset: usize,
+    rows_read: usize,
+    /// Bytes of an incomplete row found at the end of the stream.
+    remainder: usize,
+    finished: bool,
+    /// Set once the `Iterator` impl has returned an error.
+    failed: bool,
+    /// Rows per batch when used as an `Iterator`.
+    batch_size: usize,
+}
+
+impl<R: Read> StreamReader<R> {
+    pub fn new(reader: R, schema: Schema) -> Result<Self, Error> {
+        Self::with_mode(reader, schema, ReadMode::Strict)
+    }
+
+    /// In strict mode a partial row at the end of the stream is reported as
+    /// `TrailingBytes` after the last complete batch; in lenient mode it is
+    /// dropped and counted in `remainder`.
+    ///
+    /// A container header at the start of the stream is read and its schema
+    /// must equal `schema`.
+    pub fn with_mode(reader: R, schema: Schema, mode: ReadMode) -> Result<Self, Error> {
+        Self::start(reader, Some(schema), mode)
+    }
+
+    /// Reads a container stream using the schema embedded in its header.
+    pub fn from_container(reader: R, mode: ReadMode) -> Result<Self, Error> {
+        Self::start(reader, None, mode)
+    }
+
+    fn start(mut reader: R, schema: Option<Schema>, mode: ReadMode) -> Result<Self, Error> {
+        // Look at the first bytes for a header; if there is none they are
+        // the start of the first row.
+        let mut pending = Vec::with_capacity(MAGIC.len());
+        (&mut reader)
+            .take(MAGIC.len() as u64)
+            .read_to_end(&mut pending)?;
+        let (header, data_offset) = if pending == MAGIC {
+            let (header, len) = Header::read_counted(&mut pending.chain(&mut reader))?;
+            pending = Vec::new();
+            (Some(header), len)
+        } else {
+            (None, 0)
+        };
+
+        let schema = match (schema, &header) {
+            (Some(schema), Some(header)) if schema != header.schema => {
+                return Err(Error::SchemaMismatch)
+            }
+            (Some(schema), _) => schema,
+            (None, Some(header)) => header.schema.clone(),
+            (None, None) => return Err(Error::MissingHeader),
+        };
+
+        Ok(Self {
+            reader,
+            decoder: RowDecoder::new(schema)?,
+            mode,
+            buffer: Vec::new(),
+            pending,
+            header,
+            data_offset,
+            rows_read: 0,
+            remainder: 0,
+            finished: false,
+            failed: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+        })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.decoder.schema
+    }
+
+    /// The container header, if the stream has one.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Number of bytes after the last complete row, known once the end of
+    /// the stream has been reached.
+    pub fn remainder(&self) -> usize {
+        self.remainder
+    }
+
+    options::decode_options_methods!();
+    options::batch_iterator_methods!();
+
+    /// Number of rows returned so far, i.e. the index of the next row.
+    pub fn position(&self) -> usize {
+        self.rows_read
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads and decodes up to `batch_size` rows. Blocks until that many rows
+    /// are available or the stream ends; returns `None` at the end.
+    pub fn read_batch(&mut self, batch_size: usize) -> Result<Option<Batch>, Error> {
+        let row_size = self.decoder.row_size;
+        let batch_size = batch_size.max(1);
+        let wanted = batch_size * row_size;
+
+        // Bytes left over from earlier reads come first. The buffer grows
+        // with the data actually read, so a large batch size does not
+        // allocate up front.
+        self.buffer.clear();
+        self.buffer.append(&mut self.pending);
+        if !self.finished && self.buffer.len() < wanted {
+            let missing = wanted - self.buffer.len();
+            let read = (&mut self.reader)
+                .take(missing as u64)
+                .read_to_end(&mut self.buffer)?;
+            self.finished = read < missing;
+        }
+
+        let rows = self
+            .buffer
+            .len()
+            .checked_div(row_size)
+            .unwrap_or(0)
+            .min(batch_size);
+        let used = rows * row_size;
+        self.pending.extend_from_slice(&self.buffer[used..]);
+
+        if rows == 0 {
+            // Nothing but a partial row (or nothing at all) was left
+            self.remainder = self.pending.len();
+            return match (self.mode, self.remainder) {
+                (ReadMode::Strict, len) if len > 0 => Err(Error::TrailingBytes {
+                    offset: self.data_offset + self.rows_read * row_size,
+                    len,
+                }),
+                _ => Ok(None),
+            };
+        }
+
+        let offset = self.data_offset + self.rows_read * row_size;
+        let batch = self
+            .decoder
+            .decode(&self.buffer[..used], self.rows_read, offset)?;
+        self.rows_read += rows;
+        Ok(Some(batch))
+    }
+}
+
+impl StreamReader<Box<dyn Read + Send>> {
+    /// Streams the rows of a file, decompressing it on the fly if it is
+    /// gzip, zstd or lz4 compressed (see `Compression::detect`).
+    pub fn open(filename: &str, schema: Schema, mode: ReadMode) -> Result<Self, Error> {
+        let (reader, _) = open_decompressed(filename)?;
+        Self::with_mode(reader, schema, mode)
+    }
+
+    /// Same as `open`, but decompresses the file as `compression` instead of
+    /// detecting it.
+    pub fn open_with_compression(
+        filename: &str,
+        schema: Schema,
+        mode: ReadMode,
+        compression: Compression,
+    ) -> Result<Self, Error> {
+        Self::with_mode(compression.open(filename)?, schema, mode)
+    }
+}
+
+/// Yields consecutive batches of `batch_size()` rows. After an error the
+/// iterator is exhausted.
+impl<R: Read> Iterator for StreamReader<R> {
+    type Item = Result<Batch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read_batch(self.batch_size).transpose();
+        if let Some(Err(_)) = result {
+            self.failed = true;
+        }
+        result
+    }
+}
+
+impl<R: Read> sealed::Decoders for StreamReader<R> {
+    fn decoder(&self) -> &RowDecoder {
+        &self.decoder
+    }
+
+    fn decoders_mut(&mut self) -> impl Iterator<Item = &mut RowDecoder> {
+        std::iter::once(&mut self.decoder)
+    }
+}
+
+impl<R: Read> DecodeOptions for StreamReader<R> {}
+
+impl<R: Read> sealed::BatchSize for StreamReader<R> {
+    fn batch_rows(&self) -> usize {
+        self.batch_size
+    }
+
+    fn batch_size_mut(&mut self) -> &mut usize {
+        &mut self.batch_size
+    }
+}
+
+impl<R: Read> BatchIterator for StreamReader<R> {}
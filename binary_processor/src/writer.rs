@@ -0,0 +1,454 @@
+use crate::decode::ColumnLayout;
+use crate::{Batch, ChannelData, DataType, Endianness, Error, Header, Schema, Value};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::io::Write;
+use std::mem::discriminant;
+
+/// Bytes of rows encoded at a time before they are passed to the writer.
+const WRITE_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Writes rows in the layout described by a schema, producing exactly the
+/// bytes `BatchReader` decodes.
+///
+/// Values are raw channel values, one per column of `Schema::columns`: packed
+/// channels take one `Value::Bool` per named bit and padding, unnamed bits
+/// and bytes past the last field are written as zeros. Timestamps are counts
+/// of `Timestamp::unit` since the Unix epoch, as in `Batch::timestamps`.
+pub struct RowWriter<W: Write> {
+    writer: W,
+    schema: Schema,
+    layouts: Vec<ColumnLayout>,
+    row_size: usize,
+    /// Encoded rows waiting to be written, reused between calls.
+    buffer: Vec<u8>,
+    rows_written: usize,
+}
+
+impl<W: Write> RowWriter<W> {
+    pub fn new(writer: W, schema: Schema) -> Result<Self, Error> {
+        let problems = schema.validate();
+        if !problems.is_empty() {
+            return Err(Error::InvalidSchema(problems));
+        }
+        Ok(Self {
+            writer,
+            layouts: ColumnLayout::for_schema(&schema),
+            row_size: schema.row_size(),
+            schema,
+            buffer: Vec::new(),
+            rows_written: 0,
+        })
+    }
+
+    /// Writes `header` first, making the output a container that carries its
+    /// own schema.
+    pub fn with_header(writer: W, header: &Header) -> Result<Self, Error> {
+        let mut row_writer = Self::new(writer, header.schema.clone())?;
+        header.write_to(&mut row_writer.writer)?;
+        Ok(row_writer)
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn rows_written(&self) -> usize {
+        self.rows_written
+    }
+
+    /// Writes one row. `timestamp` must be given exactly when the schema has
+    /// a timestamp field.
+    pub fn write_row(&mut self, timestamp: Option<i64>, values: &[Value]) -> Result<(), Error> {
+        let expected = usize::from(self.schema.timestamp.is_some());
+        if usize::from(timestamp.is_some()) != expected {
+            return Err(Error::ColumnLength {
+                column: "timestamp".to_string(),
+                len: usize::from(timestamp.is_some()),
+                expected,
+            });
+        }
+        if values.len() != self.layouts.len() {
+            return Err(Error::ColumnCount {
+                expected: self.layouts.len(),
+                actual: values.len(),
+            });
+        }
+
+        self.buffer.clear();
+        self.buffer.resize(self.row_size, 0);
+        if let (Some(encoding), Some(ts)) = (&self.schema.timestamp, timestamp) {
+            encoding.write(&mut &mut self.buffer[..], self.schema.byte_order, ts)?;
+        }
+        for (idx, (layout, &value)) in self.layouts.iter().zip(values).enumerate() {
+            if !layout.encode(&mut self.buffer, value) {
+                return Err(self.type_error(idx));
+            }
+        }
+        self.writer.write_all(&self.buffer)?;
+        self.rows_written += 1;
+        Ok(())
+    }
+
+    /// Writes one row per value of `columns`, which hold every column of
+    /// `Schema::columns` in order. `timestamps` must have one value per row,
+    /// or be empty if the schema has no timestamp field. Nothing is written
+    /// if the columns do not match the schema or a timestamp is out of range.
+    pub fn write_columns(
+        &mut self,
+        timestamps: &[i64],
+        columns: &[ChannelData],
+    ) -> Result<(), Error> {
+        if columns.len() != self.layouts.len() {
+            return Err(Error::ColumnCount {
+                expected: self.layouts.len(),
+                actual: columns.len(),
+            });
+        }
+        let rows = match self.schema.timestamp {
+            Some(_) => timestamps.len(),
+            None => columns.first().map_or(0, |c| c.len()),
+        };
+        if self.schema.timestamp.is_none() && !timestamps.is_empty() {
+            return Err(Error::ColumnLength {
+                column: "timestamp".to_string(),
+                len: timestamps.len(),
+                expected: 0,
+            });
+        }
+        let names = self.schema.columns();
+        for (idx, (layout, data)) in self.layouts.iter().zip(columns).enumerate() {
+            if discriminant(data) != discriminant(&layout.new_data(0)) {
+                return Err(self.type_error(idx));
+            }
+            if data.len() != rows {
+                return Err(Error::ColumnLength {
+                    column: names[idx].name.to_string(),
+                    len: data.len(),
+                    expected: rows,
+                });
+            }
+        }
+        if let Some(encoding) = &self.schema.timestamp {
+            for &ts in timestamps {
+                encoding.to_raw(ts)?;
+            }
+        }
+
+        let chunk_rows = (WRITE_CHUNK_BYTES / self.row_size).max(1);
+        for start in (0..rows).step_by(chunk_rows) {
+            let count = chunk_rows.min(rows - start);
+            self.buffer.clear();
+            self.buffer.resize(count * self.row_size, 0);
+            for (i, row) in self.buffer.chunks_exact_mut(self.row_size).enumerate() {
+                if let Some(encoding) = &self.schema.timestamp {
+                    encoding.write(
+                        &mut &mut row[..],
+                        self.schema.byte_order,
+                        timestamps[start + i],
+                    )?;
+                }
+                for (layout, data) in self.layouts.iter().zip(columns) {
+                    let value = data.get(start + i).expect("column lengths were checked");
+                    layout.encode(row, value);
+                }
+            }
+            self.writer.write_all(&self.buffer)?;
+            self.rows_written += count;
+        }
+        Ok(())
+    }
+
+    /// Writes the rows of `batch`. The batch must hold all columns with raw
+    /// values, i.e. come from a reader without a column selection or
+    /// engineering values.
+    pub fn write_batch(&mut self, batch: &Batch) -> Result<(), Error> {
+        self.write_columns(&batch.timestamps, &batch.columns)
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> Result<W, Error> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    fn type_error(&self, column: usize) -> Error {
+        Error::ColumnType {
+            column: self.schema.columns()[column].name.to_string(),
+        }
+    }
+}
+
+impl ColumnLayout {
+    /// Stores `value` in `row`, a zero-initialised row. Returns `false` if the
+    /// value does not have this column's type.
+    pub(crate) fn encode(&self, row: &mut [u8], value: Value) -> bool {
+        let bytes = &mut row[self.offset..self.offset + self.data_type.size()];
+        match (self.bit, value) {
+            (Some(bit), Value::Bool(flag)) => {
+                // The named bit counts from the least significant end of the word
+                let size = self.data_type.size().min(8);
+                let byte = match self.order {
+                    Endianness::Little => bit as usize / 8,
+                    Endianness::Big => size - 1 - bit as usize / 8,
+                };
+                bytes[byte] |= u8::from(flag) << (bit % 8);
+                true
+            }
+            (Some(_), _) => false,
+            (None, value) => match self.order {
+                Endianness::Little => encode_as::<LittleEndian>(bytes, self.data_type, value),
+                Endianness::Big => encode_as::<BigEndian>(bytes, self.data_type, value),
+            },
+        }
+    }
+}
+
+fn encode_as<B: ByteOrder>(bytes: &mut [u8], data_type: DataType, value: Value) -> bool {
+    match (data_type, value) {
+        (DataType::Bit, Value::Bit(v)) => bytes[0] = v,
+        (DataType::Int, Value::Int(v)) => B::write_i32(bytes, v),
+        (DataType::Float, Value::Float(v)) => B::write_f64(bytes, v),
+        (DataType::I8, Value::I8(v)) => bytes[0] = v as u8,
+        (DataType::I16, Value::I16(v)) => B::write_i16(bytes, v),
+        (DataType::I64, Value::I64(v)) => B::write_i64(bytes, v),
+        (DataType::U16, Value::U16(v)) => B::write_u16(bytes, v),
+        (DataType::U32, Value::U32(v)) => B::write_u32(bytes, v),
+        (DataType::U64, Value::U64(v)) => B::write_u64(bytes, v),
+        (DataType::F32, Value::F32(v)) => B::write_f32(bytes, v),
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::RowDecoder;
+    use crate::{
+        BatchReader, BitField, Channel, SchemaProblem, StreamReader, Timestamp, TimestampEncoding,
+    };
+
+    /// More rows than `write_columns` encodes in one chunk.
+    const ROWS: usize = 5000;
+
+    /// A channel of every type, a padding gap, packed bits, a channel in the
+    /// opposite byte order and trailing padding after the last field.
+    fn schema(byte_order: Endianness, encoding: TimestampEncoding) -> Schema {
+        let other = match byte_order {
+            Endianness::Little => Endianness::Big,
+            Endianness::Big => Endianness::Little,
+        };
+        let mut flags = Channel::new("flags", DataType::U16);
+        flags.bits = [("low", 0), ("mid", 9), ("high", 15)]
+            .into_iter()
+            .map(|(name, position)| BitField {
+                name: name.to_string(),
+                position,
+            })
+            .collect();
+        let mut swapped = Channel::new("swapped", DataType::U32);
+        swapped.byte_order = Some(other);
+        let mut schema = Schema {
+            channels: vec![
+                Channel::new("bit", DataType::Bit),
+                Channel::new("int", DataType::Int),
+                Channel::new("float", DataType::Float),
+                Channel::new("gap", DataType::Padding(3)),
+                Channel::new("i8", DataType::I8),
+                Channel::new("i16", DataType::I16),
+                Channel::new("i64", DataType::I64),
+                Channel::new("u16", DataType::U16),
+                Channel::new("u32", DataType::U32),
+                Channel::new("u64", DataType::U64),
+                Channel::new("f32", DataType::F32),
+                flags,
+                swapped,
+            ],
+            timestamp: Some(Timestamp {
+                encoding,
+                epoch: None,
+                timezone: None,
+            }),
+            byte_order,
+            row_size: None,
+        };
+        schema.row_size = Some(schema.row_size() + 5);
+        schema
+    }
+
+    fn timestamps(schema: &Schema) -> Vec<i64> {
+        // Whole milliseconds survive every encoding; ticks start at zero
+        let start = match schema.timestamp.as_ref().map(|t| t.encoding) {
+            Some(TimestampEncoding::U32Ticks { .. }) => 0,
+            _ => 1_700_000_000_000,
+        };
+        let per_ms = match schema.timestamp.as_ref().map(|t| t.unit()) {
+            Some(crate::TimeUnit::Nanosecond) => 1_000_000,
+            _ => 1_000,
+        };
+        (0..ROWS as i64).map(|i| (start + i) * per_ms).collect()
+    }
+
+    fn columns() -> Vec<ChannelData> {
+        let rows = 0..ROWS;
+        vec![
+            ChannelData::Bit(rows.clone().map(|i| i as u8).collect()),
+            ChannelData::Int(rows.clone().map(|i| i as i32 * -70_001).collect()),
+            ChannelData::Float(rows.clone().map(|i| i as f64 / 3.0 - 100.0).collect()),
+            ChannelData::I8(rows.clone().map(|i| (i as i64 - 128) as i8).collect()),
+            ChannelData::I16(rows.clone().map(|i| (i as i16).wrapping_mul(-31)).collect()),
+            ChannelData::I64(rows.clone().map(|i| i64::MIN + i as i64).collect()),
+            ChannelData::U16(rows.clone().map(|i| (i * 65) as u16).collect()),
+            ChannelData::U32(rows.clone().map(|i| u32::MAX - i as u32).collect()),
+            ChannelData::U64(rows.clone().map(|i| u64::MAX / (i as u64 + 1)).collect()),
+            ChannelData::F32(rows.clone().map(|i| i as f32 * 0.25).collect()),
+            ChannelData::Bool(rows.clone().map(|i| i % 2 == 0).collect()),
+            ChannelData::Bool(rows.clone().map(|i| i % 3 == 0).collect()),
+            ChannelData::Bool(rows.clone().map(|i| i % 5 == 0).collect()),
+            ChannelData::U32(rows.map(|i| 0x0102_0304 + i as u32).collect()),
+        ]
+    }
+
+    fn cases() -> Vec<Schema> {
+        vec![
+            schema(Endianness::Little, TimestampEncoding::F64Millis),
+            schema(Endianness::Little, TimestampEncoding::I64Micros),
+            schema(Endianness::Big, TimestampEncoding::I64Nanos),
+            schema(
+                Endianness::Big,
+                TimestampEncoding::U32Ticks { tick_rate: 1000.0 },
+            ),
+        ]
+    }
+
+    #[test]
+    fn write_columns_round_trips_through_readers() {
+        for (case, schema) in cases().into_iter().enumerate() {
+            let expected = Batch {
+                start_row: 0,
+                num_rows: ROWS,
+                timestamps: timestamps(&schema),
+                columns: columns(),
+            };
+            let mut writer = RowWriter::new(Vec::new(), schema.clone()).unwrap();
+            writer.write_batch(&expected).unwrap();
+            assert_eq!(writer.rows_written(), ROWS);
+            let bytes = writer.into_inner().unwrap();
+            assert_eq!(bytes.len(), ROWS * schema.row_size());
+
+            let mut stream = StreamReader::new(&bytes[..], schema.clone()).unwrap();
+            assert_eq!(stream.read_batch(ROWS).unwrap().as_ref(), Some(&expected));
+            assert!(stream.read_batch(ROWS).unwrap().is_none());
+
+            let path = std::env::temp_dir().join(format!(
+                "row_writer_{}_{}.bin",
+                std::process::id(),
+                case
+            ));
+            std::fs::write(&path, &bytes).unwrap();
+            let mut reader = BatchReader::new(path.to_str().unwrap(), schema).unwrap();
+            let batch = reader.read_batch(ROWS).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(batch.as_ref(), Some(&expected));
+        }
+    }
+
+    #[test]
+    fn write_row_matches_write_columns() {
+        for schema in cases() {
+            let ts = timestamps(&schema);
+            let columns = columns();
+            let mut by_columns = RowWriter::new(Vec::new(), schema.clone()).unwrap();
+            by_columns.write_columns(&ts, &columns).unwrap();
+
+            let mut by_rows = RowWriter::new(Vec::new(), schema).unwrap();
+            for (row, &t) in ts.iter().enumerate() {
+                let values: Vec<Value> = columns.iter().map(|c| c.get(row).unwrap()).collect();
+                by_rows.write_row(Some(t), &values).unwrap();
+            }
+            assert_eq!(
+                by_rows.into_inner().unwrap(),
+                by_columns.into_inner().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn padding_and_unnamed_bits_are_zero() {
+        let schema = schema(Endianness::Big, TimestampEncoding::I64Nanos);
+        let mut writer = RowWriter::new(Vec::new(), schema.clone()).unwrap();
+        writer
+            .write_columns(&timestamps(&schema), &columns())
+            .unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let offsets = schema.channel_offsets();
+        let gap = offsets[3]..offsets[3] + 3;
+        let tail = schema.row_size() - 5..schema.row_size();
+        // Bits 0, 9 and 15 of the big-endian flags word
+        let flags_mask = [0b1000_0010, 0b0000_0001];
+        for row in bytes.chunks_exact(schema.row_size()) {
+            assert!(row[gap.clone()].iter().all(|&b| b == 0));
+            assert!(row[tail.clone()].iter().all(|&b| b == 0));
+            let flags = &row[offsets[11]..offsets[11] + 2];
+            assert_eq!(flags[0] & !flags_mask[0], 0);
+            assert_eq!(flags[1] & !flags_mask[1], 0);
+        }
+    }
+
+    #[test]
+    fn out_of_range_timestamp_writes_nothing() {
+        let schema = schema(
+            Endianness::Little,
+            TimestampEncoding::U32Ticks { tick_rate: 1000.0 },
+        );
+        let mut ts = timestamps(&schema);
+        ts[ROWS - 1] = -1_000_000;
+        let mut writer = RowWriter::new(Vec::new(), schema).unwrap();
+        let result = writer.write_columns(&ts, &columns());
+        assert!(matches!(
+            result,
+            Err(Error::TimestampOutOfRange(-1_000_000))
+        ));
+        assert_eq!(writer.rows_written(), 0);
+        assert!(writer.get_ref().is_empty());
+    }
+
+    #[test]
+    fn overflowing_layout_is_rejected() {
+        let mut far = Channel::new("far", DataType::Int);
+        far.byte_offset = Some(usize::MAX - 1);
+        for channels in [
+            vec![far],
+            vec![
+                Channel::new("gap", DataType::Padding(usize::MAX - 1)),
+                Channel::new("int", DataType::Int),
+            ],
+        ] {
+            let schema = Schema {
+                channels,
+                timestamp: None,
+                byte_order: Endianness::Little,
+                row_size: None,
+            };
+            let expected = SchemaProblem::OffsetOverflow {
+                channel: schema.channels.len() - 1,
+            };
+            assert_eq!(schema.validate(), vec![expected.clone()]);
+            let Err(Error::InvalidSchema(problems)) = RowDecoder::new(schema.clone()) else {
+                panic!("decoder accepted an overflowing layout");
+            };
+            assert_eq!(problems, vec![expected]);
+            assert!(RowWriter::new(Vec::new(), schema).is_err());
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use arrow::array::{ArrayRef, AsArray};
+use arrow::datatypes::{
+    ArrowPrimitiveType, DataType as ArrowType, Field, Float32Type, Float64Type, Int16Type,
+    Int32Type, Int64Type, Int8Type, Schema as ArrowSchema, TimeUnit as ArrowTimeUnit,
+    TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+    TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use binary_processor::{
+    BitField, Channel, ChannelData, DataType, Endianness, RowWriter, Schema, TimeUnit, Timestamp,
+    TimestampEncoding,
+};
+use clap::Parser;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use sensor_reader::SCHEMA_KEY;
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Instant;
+
+/// Converts a Parquet file back into binary rows and a schema.json, e.g. to
+/// replay a recording. Converting the output with data_converter gives the
+/// same columns, types and values as the input. Files that carry the schema
+/// data_converter stored get their original row layout back.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Input parquet file
+    #[arg(short, long, default_value = ".data/output.parquet")]
+    input: String,
+
+    /// Output binary file
+    #[arg(short, long, default_value = ".data/replay.bin")]
+    output: String,
+
+    /// Where to write the schema of the output
+    #[arg(short, long, default_value = ".data/replay_schema.json")]
+    schema: String,
+
+    /// Rows read from the Parquet file at a time
+    #[arg(long, default_value_t = 65536)]
+    batch_size: usize,
+}
+
+/// Most boolean columns packed into one channel.
+const MAX_FLAGS: usize = 64;
+
+/// Channel type for a non-boolean Arrow column, the inverse of the mapping
+/// data_converter uses.
+fn channel_type(arrow_type: &ArrowType) -> Option<DataType> {
+    Some(match arrow_type {
+        ArrowType::UInt8 => DataType::Bit,
+        ArrowType::Int32 => DataType::Int,
+        ArrowType::Float64 => DataType::Float,
+        ArrowType::Int8 => DataType::I8,
+        ArrowType::Int16 => DataType::I16,
+        ArrowType::Int64 => DataType::I64,
+        ArrowType::UInt16 => DataType::U16,
+        ArrowType::UInt32 => DataType::U32,
+        ArrowType::UInt64 => DataType::U64,
+        ArrowType::Float32 => DataType::F32,
+        _ => return None,
+    })
+}
+
+/// Packs a run of boolean columns into the bits of one unsigned channel,
+/// which data_converter expands back into one boolean column per bit.
+fn flags_channel(index: usize, names: &mut Vec<String>) -> Channel {
+    let data_type = match names.len() {
+        0..=8 => DataType::Bit,
+        9..=16 => DataType::U16,
+        17..=32 => DataType::U32,
+        _ => DataType::U64,
+    };
+    let mut channel = Channel::new(format!("flags_{}", index), data_type);
+    channel.bits = names
+        .drain(..)
+        .enumerate()
+        .map(|(position, name)| BitField {
+            name,
+            position: position as u8,
+        })
+        .collect();
+    channel
+}
+
+/// Channel for a non-boolean column, with the unit, description and scaling
+/// data_converter stores as field metadata.
+fn value_channel(field: &Field) -> anyhow::Result<Channel> {
+    let data_type = channel_type(field.data_type()).ok_or_else(|| {
+        anyhow::anyhow!(
+            "column '{}' has unsupported type {}",
+            field.name(),
+            field.data_type()
+        )
+    })?;
+    let metadata = field.metadata();
+    let number = |key: &str| -> anyhow::Result<Option<f64>> {
+        match metadata.get(key) {
+            Some(v) => Ok(Some(v.parse().map_err(|e| {
+                anyhow::anyhow!(
+                    "invalid {} '{}' on column '{}': {}",
+                    key,
+                    v,
+                    field.name(),
+                    e
+                )
+            })?)),
+            None => Ok(None),
+        }
+    };
+    let mut channel = Channel::new(field.name().clone(), data_type);
+    channel.unit = metadata.get("unit").cloned();
+    channel.description = metadata.get("description").cloned();
+    channel.scale = number("scale")?;
+    channel.offset = number("offset")?;
+    Ok(channel)
+}
+
+/// Reconstructs a little-endian row schema from an Arrow schema. A column
+/// named `timestamp` becomes the leading timestamp field; consecutive
+/// boolean columns are packed into bit-field channels.
+fn schema_from_arrow(arrow_schema: &ArrowSchema) -> anyhow::Result<Schema> {
+    let mut channels = Vec::new();
+    let mut timestamp = None;
+    let mut flags = Vec::new();
+
+    for field in arrow_schema.fields() {
+        if field.name() == "timestamp" {
+            let ArrowType::Timestamp(unit, timezone) = field.data_type() else {
+                anyhow::bail!(
+                    "column 'timestamp' has type {}, expected a timestamp",
+                    field.data_type()
+                );
+            };
+            // Second and millisecond timestamps are widened to microseconds
+            let encoding = match unit {
+                ArrowTimeUnit::Nanosecond => TimestampEncoding::I64Nanos,
+                _ => TimestampEncoding::I64Micros,
+            };
+            timestamp = Some(Timestamp {
+                encoding,
+                epoch: None,
+                timezone: timezone.as_ref().map(|tz| tz.to_string()),
+            });
+            continue;
+        }
+        if field.data_type() == &ArrowType::Boolean {
+            flags.push(field.name().clone());
+            if flags.len() == MAX_FLAGS {
+                channels.push(flags_channel(channels.len(), &mut flags));
+            }
+            continue;
+        }
+        if !flags.is_empty() {
+            channels.push(flags_channel(channels.len(), &mut flags));
+        }
+        channels.push(value_channel(field)?);
+    }
+    if !flags.is_empty() {
+        channels.push(flags_channel(channels.len(), &mut flags));
+    }
+
+    Ok(Schema {
+        channels,
+        timestamp,
+        byte_order: Endianness::Little,
+        row_size: None,
+    })
+}
+
+/// Whether the columns of `arrow_schema` are exactly those data_converter
+/// writes for `schema`, i.e. it ran without --channels or --engineering.
+fn matches_columns(schema: &Schema, arrow_schema: &ArrowSchema) -> bool {
+    let mut fields = arrow_schema.fields().iter();
+    if let Some(timestamp) = &schema.timestamp {
+        let unit = match timestamp.unit() {
+            TimeUnit::Microsecond => ArrowTimeUnit::Microsecond,
+            TimeUnit::Nanosecond => ArrowTimeUnit::Nanosecond,
+        };
+        match fields.next() {
+            Some(field) if field.name() == "timestamp" => {
+                if !matches!(field.data_type(), ArrowType::Timestamp(u, _) if *u == unit) {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+    let columns = schema.columns();
+    fields.len() == columns.len()
+        && fields.zip(&columns).all(|(field, column)| {
+            let same_type = match column.bit {
+                Some(_) => field.data_type() == &ArrowType::Boolean,
+                None => {
+                    let channel = &schema.channels[column.channel];
+                    // --engineering drops the scale and offset metadata of
+                    // scaled channels, even when their type is unchanged
+                    channel_type(field.data_type()) == Some(channel.data_type)
+                        && (channel.scaling().is_none() || field.metadata().contains_key("scale"))
+                }
+            };
+            field.name() == column.name && same_type
+        })
+}
+
+fn values<T: ArrowPrimitiveType>(array: &ArrayRef) -> Vec<T::Native> {
+    array.as_primitive::<T>().values().to_vec()
+}
+
+/// Timestamps in the unit of the reconstructed schema.
+fn timestamps(array: &ArrayRef) -> anyhow::Result<Vec<i64>> {
+    let ArrowType::Timestamp(unit, _) = array.data_type() else {
+        anyhow::bail!("timestamp column has type {}", array.data_type());
+    };
+    let widen = |v: Vec<i64>, factor: i64| -> anyhow::Result<Vec<i64>> {
+        v.into_iter()
+            .map(|t| {
+                t.checked_mul(factor)
+                    .ok_or_else(|| anyhow::anyhow!("timestamp {} is out of range", t))
+            })
+            .collect()
+    };
+    match unit {
+        ArrowTimeUnit::Second => widen(values::<TimestampSecondType>(array), 1_000_000),
+        ArrowTimeUnit::Millisecond => widen(values::<TimestampMillisecondType>(array), 1_000),
+        ArrowTimeUnit::Microsecond => Ok(values::<TimestampMicrosecondType>(array)),
+        ArrowTimeUnit::Nanosecond => Ok(values::<TimestampNanosecondType>(array)),
+    }
+}
+
+/// Column values in the `ChannelData` variant `RowWriter` expects for them.
+fn channel_data(array: &ArrayRef) -> anyhow::Result<ChannelData> {
+    Ok(match array.data_type() {
+        ArrowType::UInt8 => ChannelData::Bit(values::<UInt8Type>(array)),
+        ArrowType::Int32 => ChannelData::Int(values::<Int32Type>(array)),
+        ArrowType::Float64 => ChannelData::Float(values::<Float64Type>(array)),
+        ArrowType::Int8 => ChannelData::I8(values::<Int8Type>(array)),
+        ArrowType::Int16 => ChannelData::I16(values::<Int16Type>(array)),
+        ArrowType::Int64 => ChannelData::I64(values::<Int64Type>(array)),
+        ArrowType::UInt16 => ChannelData::U16(values::<UInt16Type>(array)),
+        ArrowType::UInt32 => ChannelData::U32(values::<UInt32Type>(array)),
+        ArrowType::UInt64 => ChannelData::U64(values::<UInt64Type>(array)),
+        ArrowType::Float32 => ChannelData::F32(values::<Float32Type>(array)),
+        ArrowType::Boolean => ChannelData::Bool(array.as_boolean().values().iter().collect()),
+        dt => anyhow::bail!("unsupported data type {}", dt),
+    })
+}
+
+fn main() -> anyhow::Result<()> {
+    let start = Instant::now();
+    let args = Args::parse();
+
+    println!("Reading {}...", args.input);
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&args.input)?)?
+        .with_batch_size(args.batch_size.max(1));
+    let arrow_schema = builder.schema().clone();
+    let total_rows = builder.metadata().file_metadata().num_rows();
+
+    let stored = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|kv| kv.iter().find(|entry| entry.key == SCHEMA_KEY))
+        .and_then(|entry| entry.value.as_deref())
+        .and_then(|json| serde_json::from_str::<Schema>(json).ok())
+        .filter(|schema| matches_columns(schema, &arrow_schema));
+    let schema = match stored {
+        Some(schema) => {
+            println!("Using the original schema stored in {}", args.input);
+            schema
+        }
+        None => schema_from_arrow(&arrow_schema)?,
+    };
+    let problems = schema.validate();
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("Schema problem: {}", problem);
+        }
+        anyhow::bail!(
+            "the columns of {} do not form a valid schema ({} problem(s))",
+            args.input,
+            problems.len()
+        );
+    }
+    std::fs::write(&args.schema, serde_json::to_string_pretty(&schema)?)?;
+    println!(
+        "Wrote schema with {} channels to {}",
+        schema.channels.len(),
+        args.schema
+    );
+
+    let output = BufWriter::new(File::create(&args.output)?);
+    let mut writer = RowWriter::new(output, schema)?;
+    for batch in builder.build()? {
+        let batch = batch?;
+        let mut ts = Vec::new();
+        let mut columns = Vec::with_capacity(batch.num_columns());
+        for (field, array) in arrow_schema.fields().iter().zip(batch.columns()) {
+            // Rows cannot hold missing values
+            if array.null_count() > 0 {
+                anyhow::bail!("column '{}' contains null values", field.name());
+            }
+            if field.name() == "timestamp" {
+                ts = timestamps(array)?;
+            } else {
+                columns.push(channel_data(array)?);
+            }
+        }
+        writer.write_columns(&ts, &columns)?;
+        println!("Processed {} / {} rows", writer.rows_written(), total_rows);
+    }
+    writer.into_inner()?;
+
+    println!("Conversion complete. Output saved to {}", args.output);
+    println!("Duration: {} ms", start.elapsed().as_millis());
+    Ok(())
+}
@@ -1,33 +1,699 @@
-use arrow::array::{ArrayRef, Float64Array, Int32Array, UInt8Array};
-use arrow::datatypes::{DataType as ArrowType, Field, Schema as ArrowSchema};
+use arrow::array::{
+    ArrayRef, BooleanArray, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array,
+    Int8Array, TimestampMicrosecondArray, TimestampNanosecondArray, UInt16Array, UInt32Array,
+    UInt64Array, UInt8Array,
+};
+use arrow::datatypes::{
+    DataType as ArrowType, Field, Schema as ArrowSchema, TimeUnit as ArrowTimeUnit,
+};
 use arrow::record_batch::RecordBatch;
-use binary_processor::{BatchReader, ChannelData, DataType, Schema};
-use clap::Parser;
+use binary_processor::{
+    decompress, glob_paths, open_decompressed, Batch, BatchReader, ChannelData, Column,
+    Compression as InputCompression, DataType, FollowReader, Header, MultiFileReader, ReadMode,
+    Schema, StreamReader, TimeUnit, MAGIC,
+};
+use clap::{Parser, ValueEnum};
 use parquet::arrow::ArrowWriter;
-use parquet::basic::Compression;
-use parquet::file::properties::WriterProperties;
+use parquet::basic::{Compression, Encoding, GzipLevel, ZstdLevel};
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::{EnabledStatistics, WriterProperties, WriterPropertiesBuilder};
+use parquet::schema::types::ColumnPath;
+use sensor_reader::{SCHEMA_KEY, SOURCES_KEY, VERSION_KEY};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Input binary file
-    #[arg(short, long, default_value = ".data/data.bin")]
-    input: String,
+    /// Input binary file, or `-` to read rows from stdin. Gzip, zstd and lz4
+    /// compressed input is detected (see --input-compression) and
+    /// decompressed on the fly. Several files or a glob pattern (e.g.
+    /// "data_*.bin") are read as one recording; with --start/--end they must
+    /// be uncompressed
+    #[arg(short, long, num_args = 1.., default_value = ".data/data.bin")]
+    input: Vec<String>,
+
+    /// Compression of the input. `auto` goes by the file extension, then the
+    /// first bytes, which raw rows can match by chance
+    #[arg(long, value_enum, default_value_t = InputCodec::Auto)]
+    input_compression: InputCodec,
 
     /// Output parquet file
     #[arg(short, long, default_value = ".data/output.parquet")]
     output: String,
 
-    /// Schema file
-    #[arg(short, long, default_value = ".data/schema.json")]
-    schema: String,
+    /// Schema file. Defaults to the schema embedded in a container input,
+    /// or .data/schema.json for headerless input
+    #[arg(short, long)]
+    schema: Option<String>,
 
     /// Memory limit in MB (approximate)
     #[arg(short, long, default_value_t = 1024)]
     memory_limit_mb: usize,
+
+    /// Skip a trailing partial row instead of refusing the input
+    #[arg(long)]
+    lenient: bool,
+
+    /// Comma-separated list of channels to convert (default: all)
+    #[arg(long, value_delimiter = ',')]
+    channels: Vec<String>,
+
+    /// Only convert rows at or after this time (RFC 3339, or a number in the
+    /// timestamp column's unit)
+    #[arg(long)]
+    start: Option<String>,
+
+    /// Only convert rows before this time (same format as --start)
+    #[arg(long)]
+    end: Option<String>,
+
+    /// Decoder threads (0 = one per core)
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// Write scaled channels as engineering values (raw * scale + offset)
+    #[arg(long)]
+    engineering: bool,
+
+    /// Keep converting rows as they are appended to the input. Output goes to
+    /// numbered files next to --output (e.g. output_0000.parquet)
+    #[arg(long)]
+    follow: bool,
+
+    /// With --follow, start a new Parquet file after this many rows
+    #[arg(long)]
+    roll_rows: Option<usize>,
+
+    /// With --follow, start a new Parquet file after this many seconds
+    #[arg(long)]
+    roll_secs: Option<u64>,
+
+    /// With --follow, how often to check the input for new rows
+    #[arg(long, default_value_t = 500)]
+    poll_ms: u64,
+
+    /// With --follow, stop after this many seconds without new rows. Ctrl-C
+    /// or SIGTERM also stop following after closing the current file
+    #[arg(long)]
+    idle_timeout: Option<u64>,
+
+    /// Compression codec of the Parquet output
+    #[arg(long, value_enum, default_value_t = Codec::None)]
+    compression: Codec,
+
+    /// Compression level for gzip (0-10) or zstd (1-22)
+    #[arg(long)]
+    compression_level: Option<i32>,
+
+    /// Disable dictionary encoding for all columns
+    #[arg(long)]
+    no_dictionary: bool,
+
+    /// Encoding of a column as NAME=ENCODING, e.g. timestamp=delta_binary_packed
+    /// or temp=byte_stream_split. Repeat or comma-separate for several columns
+    #[arg(long = "encoding", value_delimiter = ',')]
+    encodings: Vec<String>,
+
+    /// Target size of a data page in bytes
+    #[arg(long)]
+    data_page_size: Option<usize>,
+
+    /// Level of min/max statistics written for each column
+    #[arg(long, value_enum, default_value_t = Statistics::Page)]
+    statistics: Statistics,
+}
+
+/// Compression of the input rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputCodec {
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+impl InputCodec {
+    /// The compression to read the input with, or `None` to detect it.
+    fn compression(self) -> Option<InputCompression> {
+        match self {
+            InputCodec::Auto => None,
+            InputCodec::None => Some(InputCompression::None),
+            InputCodec::Gzip => Some(InputCompression::Gzip),
+            InputCodec::Zstd => Some(InputCompression::Zstd),
+            InputCodec::Lz4 => Some(InputCompression::Lz4),
+        }
+    }
+}
+
+/// Opens `path` decompressed as --input-compression says.
+fn open_input(
+    path: &str,
+    codec: InputCodec,
+) -> std::io::Result<(Box<dyn Read + Send>, InputCompression)> {
+    match codec.compression() {
+        Some(compression) => Ok((compression.open(path)?, compression)),
+        None => open_decompressed(path),
+    }
+}
+
+/// Compression codec of the Parquet output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Codec {
+    None,
+    Snappy,
+    Gzip,
+    Lz4,
+    Zstd,
+}
+
+/// Statistics written to the Parquet output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Statistics {
+    /// No statistics
+    None,
+    /// Per column chunk (row group)
+    Chunk,
+    /// Per column chunk and per data page
+    Page,
+}
+
+/// Where the rows come from: a memory-mapped file with random access, or
+/// forward-only streams such as stdin.
+enum Input {
+    File(Box<BatchReader>),
+    Stream(Streams),
+    Files(MultiFileReader),
+}
+
+impl Input {
+    fn read_batch(&mut self, batch_size: usize) -> Result<Option<Batch>, binary_processor::Error> {
+        match self {
+            Input::File(reader) => reader.read_batch_parallel(batch_size),
+            Input::Stream(streams) => streams.read_batch(batch_size),
+            Input::Files(reader) => reader.read_batch_parallel(batch_size),
+        }
+    }
+}
+
+/// Streams read one after the other, such as the files of a compressed
+/// recording set, which cannot be decompressed into memory all at once.
+struct Streams {
+    readers: Vec<(String, StreamReader<Box<dyn Read + Send>>)>,
+    /// Index of the reader batches are read from.
+    current: usize,
+    /// Last timestamp of the previous stream, which the next one must not
+    /// start before.
+    previous: Option<i64>,
+}
+
+impl Streams {
+    fn read_batch(&mut self, batch_size: usize) -> Result<Option<Batch>, binary_processor::Error> {
+        let multiple = self.readers.len() > 1;
+        // Set once a stream ends, so that the next batch is the first of a
+        // new stream
+        let mut advanced = false;
+        while let Some((path, reader)) = self.readers.get_mut(self.current) {
+            let batch = reader
+                .read_batch(batch_size)
+                .map_err(|error| match multiple {
+                    true => binary_processor::Error::InFile {
+                        file: path.clone(),
+                        error: Box::new(error),
+                    },
+                    false => error,
+                })?;
+            let Some(batch) = batch else {
+                self.current += 1;
+                advanced = true;
+                continue;
+            };
+            if let (true, Some(previous), Some(&first)) =
+                (advanced, self.previous, batch.timestamps.first())
+            {
+                if first < previous {
+                    return Err(binary_processor::Error::FileOrder {
+                        file: path.clone(),
+                        previous,
+                        first,
+                    });
+                }
+            }
+            self.previous = batch.timestamps.last().copied().or(self.previous);
+            return Ok(Some(batch));
+        }
+        Ok(None)
+    }
+}
+
+/// Columns named by --channels in that order, or all columns. The readers
+/// apply the same selection with `select_columns_by_name`.
+fn output_columns<'a>(schema: &'a Schema, channels: &[String]) -> anyhow::Result<Vec<Column<'a>>> {
+    let all = schema.columns();
+    if channels.is_empty() {
+        return Ok(all);
+    }
+    channels
+        .iter()
+        .map(|name| {
+            all.iter()
+                .find(|c| c.name == name)
+                .copied()
+                .ok_or_else(|| binary_processor::Error::UnknownColumn(name.clone()).into())
+        })
+        .collect()
+}
+
+/// Arrow schema for the timestamp (if any) followed by `columns`.
+fn arrow_schema(schema: &Schema, columns: &[Column], engineering: bool) -> Arc<ArrowSchema> {
+    let mut fields = Vec::new();
+    // Add Timestamp field
+    let timezone = schema
+        .timestamp
+        .as_ref()
+        .and_then(|t| t.timezone.clone())
+        .map(Arc::<str>::from);
+    if let Some(timestamp) = &schema.timestamp {
+        let unit = match timestamp.unit() {
+            TimeUnit::Microsecond => ArrowTimeUnit::Microsecond,
+            TimeUnit::Nanosecond => ArrowTimeUnit::Nanosecond,
+        };
+        fields.push(Field::new(
+            "timestamp",
+            ArrowType::Timestamp(unit, timezone.clone()),
+            false,
+        ));
+    }
+
+    for column in columns {
+        if column.bit.is_some() {
+            fields.push(Field::new(column.name, ArrowType::Boolean, false));
+            continue;
+        }
+        let channel = &schema.channels[column.channel];
+        let scaled = engineering && channel.scaling().is_some();
+        let arrow_type = match channel.data_type {
+            _ if scaled => ArrowType::Float64,
+            DataType::Bit => ArrowType::UInt8,
+            DataType::Int => ArrowType::Int32,
+            DataType::Float => ArrowType::Float64,
+            DataType::I8 => ArrowType::Int8,
+            DataType::I16 => ArrowType::Int16,
+            DataType::I64 => ArrowType::Int64,
+            DataType::U16 => ArrowType::UInt16,
+            DataType::U32 => ArrowType::UInt32,
+            DataType::U64 => ArrowType::UInt64,
+            DataType::F32 => ArrowType::Float32,
+            DataType::Padding(_) => unreachable!("Padding channels have no column"),
+        };
+
+        // Units and descriptions travel as field metadata. Scaling factors are
+        // kept as well when raw values are written.
+        let mut metadata = HashMap::new();
+        if let Some(unit) = &channel.unit {
+            metadata.insert("unit".to_string(), unit.clone());
+        }
+        if let Some(description) = &channel.description {
+            metadata.insert("description".to_string(), description.clone());
+        }
+        if let (false, Some((scale, offset))) = (scaled, channel.scaling()) {
+            metadata.insert("scale".to_string(), scale.to_string());
+            metadata.insert("offset".to_string(), offset.to_string());
+        }
+        fields.push(Field::new(column.name, arrow_type, false).with_metadata(metadata));
+    }
+    Arc::new(ArrowSchema::new(fields))
+}
+
+/// Converts a decoded batch to Arrow arrays matching `arrow_schema`.
+fn to_record_batch(
+    batch: Batch,
+    schema: &Schema,
+    arrow_schema: &Arc<ArrowSchema>,
+) -> anyhow::Result<RecordBatch> {
+    let timezone = schema
+        .timestamp
+        .as_ref()
+        .and_then(|t| t.timezone.clone())
+        .map(Arc::<str>::from);
+    // Convert to Arrow Arrays
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(batch.columns.len() + 1);
+
+    // Add Timestamp column
+    if let Some(timestamp) = &schema.timestamp {
+        let array: ArrayRef = match timestamp.unit() {
+            TimeUnit::Microsecond => Arc::new(
+                TimestampMicrosecondArray::from(batch.timestamps)
+                    .with_timezone_opt(timezone.clone()),
+            ),
+            TimeUnit::Nanosecond => Arc::new(
+                TimestampNanosecondArray::from(batch.timestamps)
+                    .with_timezone_opt(timezone.clone()),
+            ),
+        };
+        columns.push(array);
+    }
+
+    for data in batch.columns {
+        let array: ArrayRef = match data {
+            ChannelData::Bit(v) => Arc::new(UInt8Array::from(v)),
+            ChannelData::Int(v) => Arc::new(Int32Array::from(v)),
+            ChannelData::Float(v) => Arc::new(Float64Array::from(v)),
+            ChannelData::I8(v) => Arc::new(Int8Array::from(v)),
+            ChannelData::I16(v) => Arc::new(Int16Array::from(v)),
+            ChannelData::I64(v) => Arc::new(Int64Array::from(v)),
+            ChannelData::U16(v) => Arc::new(UInt16Array::from(v)),
+            ChannelData::U32(v) => Arc::new(UInt32Array::from(v)),
+            ChannelData::U64(v) => Arc::new(UInt64Array::from(v)),
+            ChannelData::F32(v) => Arc::new(Float32Array::from(v)),
+            ChannelData::Bool(v) => Arc::new(BooleanArray::from(v)),
+        };
+        columns.push(array);
+    }
+
+    Ok(RecordBatch::try_new(arrow_schema.clone(), columns)?)
+}
+
+/// Name, size and SHA-256 of an input file. Only the name is recorded for
+/// stdin, or when `hash` is false.
+fn describe_source(path: &str, hash: bool) -> anyhow::Result<serde_json::Value> {
+    if path == "-" || !hash {
+        return Ok(serde_json::json!({ "name": path }));
+    }
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(serde_json::json!({
+        "name": path,
+        "size": size,
+        "sha256": format!("{:x}", hasher.finalize()),
+    }))
+}
+
+/// Key-value metadata for the Parquet footer: the schema the rows were
+/// decoded with, the input files and the converter version.
+fn file_metadata(
+    schema: &Schema,
+    sources: Vec<serde_json::Value>,
+) -> anyhow::Result<Vec<KeyValue>> {
+    Ok(vec![
+        KeyValue::new(SCHEMA_KEY.to_string(), serde_json::to_string(schema)?),
+        KeyValue::new(
+            SOURCES_KEY.to_string(),
+            serde_json::Value::Array(sources).to_string(),
+        ),
+        KeyValue::new(
+            VERSION_KEY.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ),
+    ])
+}
+
+/// Whether the Parquet writer can encode a column of `arrow_type` with
+/// `encoding`. Unsupported combinations would panic while writing.
+fn supports_encoding(arrow_type: &ArrowType, encoding: Encoding) -> bool {
+    let boolean = arrow_type == &ArrowType::Boolean;
+    let float = matches!(arrow_type, ArrowType::Float32 | ArrowType::Float64);
+    match encoding {
+        Encoding::PLAIN => true,
+        Encoding::RLE => boolean,
+        Encoding::DELTA_BINARY_PACKED => !boolean && !float,
+        Encoding::BYTE_STREAM_SPLIT => !boolean,
+        _ => false,
+    }
+}
+
+/// Writer properties from the compression and encoding options, checked
+/// before any input is read. The key-value metadata is added once the inputs
+/// are known.
+fn writer_properties(
+    args: &Args,
+    arrow_schema: &ArrowSchema,
+) -> anyhow::Result<WriterPropertiesBuilder> {
+    let compression = match (args.compression, args.compression_level) {
+        (Codec::None, None) => Compression::UNCOMPRESSED,
+        (Codec::Snappy, None) => Compression::SNAPPY,
+        (Codec::Lz4, None) => Compression::LZ4_RAW,
+        (Codec::Gzip, level) => Compression::GZIP(match level {
+            Some(level) => GzipLevel::try_new(u32::try_from(level).unwrap_or(u32::MAX))?,
+            None => GzipLevel::default(),
+        }),
+        (Codec::Zstd, level) => Compression::ZSTD(match level {
+            Some(level) => ZstdLevel::try_new(level)?,
+            None => ZstdLevel::default(),
+        }),
+        (codec, Some(_)) => {
+            anyhow::bail!("--compression-level is not supported for {:?}", codec)
+        }
+    };
+    let statistics = match args.statistics {
+        Statistics::None => EnabledStatistics::None,
+        Statistics::Chunk => EnabledStatistics::Chunk,
+        Statistics::Page => EnabledStatistics::Page,
+    };
+
+    let mut builder = WriterProperties::builder()
+        .set_compression(compression)
+        .set_dictionary_enabled(!args.no_dictionary)
+        .set_statistics_enabled(statistics);
+    if let Some(size) = args.data_page_size {
+        builder = builder.set_data_page_size_limit(size);
+    }
+    for spec in &args.encodings {
+        let (name, encoding) = spec.split_once('=').ok_or_else(|| {
+            anyhow::anyhow!("invalid --encoding '{}', expected NAME=ENCODING", spec)
+        })?;
+        let field = arrow_schema.field_with_name(name).map_err(|_| {
+            anyhow::anyhow!("--encoding names column '{}', which is not converted", name)
+        })?;
+        let encoding: Encoding = encoding
+            .parse()
+            .map_err(|e| anyhow::anyhow!("invalid --encoding '{}': {}", spec, e))?;
+        if matches!(
+            encoding,
+            Encoding::PLAIN_DICTIONARY | Encoding::RLE_DICTIONARY
+        ) {
+            anyhow::bail!("dictionary encoding is on by default, see --no-dictionary");
+        }
+        if !supports_encoding(field.data_type(), encoding) {
+            anyhow::bail!(
+                "{} encoding cannot be used for column '{}' of type {}",
+                encoding,
+                name,
+                field.data_type()
+            );
+        }
+        // A dictionary would be used instead of the requested encoding
+        let column = ColumnPath::from(name);
+        builder = builder
+            .set_column_encoding(column.clone(), encoding)
+            .set_column_dictionary_enabled(column, false);
+    }
+    Ok(builder)
+}
+
+fn create_writer(
+    path: &str,
+    arrow_schema: &Arc<ArrowSchema>,
+    props: &WriterProperties,
+) -> anyhow::Result<ArrowWriter<File>> {
+    let file = File::create(path)?;
+    Ok(ArrowWriter::try_new(
+        file,
+        arrow_schema.clone(),
+        Some(props.clone()),
+    )?)
+}
+
+/// Path of the `index`-th file written in follow mode: `out.parquet` becomes
+/// `out_0000.parquet`, `out_0001.parquet`, ...
+fn part_path(output: &str, index: usize) -> String {
+    let path = Path::new(output);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{:04}.{}", stem, index, ext),
+        None => format!("{}_{:04}", stem, index),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// A Parquet file being written in follow mode.
+struct Part {
+    writer: ArrowWriter<File>,
+    path: String,
+    rows: usize,
+    opened: Instant,
+}
+
+/// Converts rows as they are appended to the input, starting a new Parquet
+/// file every --roll-rows rows or --roll-secs seconds. Runs until
+/// --idle-timeout expires or the process receives SIGINT or SIGTERM; either
+/// way the current file is closed so that it has a valid footer.
+fn follow(
+    args: &Args,
+    schema: &Schema,
+    arrow_schema: &Arc<ArrowSchema>,
+    props: WriterPropertiesBuilder,
+    batch_size: usize,
+) -> anyhow::Result<()> {
+    let input = &args.input[0];
+    if args.input.len() > 1 || input == "-" || args.start.is_some() || args.end.is_some() {
+        anyhow::bail!("--follow needs one file input and cannot be combined with --start/--end");
+    }
+    let compression = args
+        .input_compression
+        .compression()
+        .unwrap_or_else(|| InputCompression::from_extension(Path::new(input)));
+    if compression != InputCompression::None {
+        anyhow::bail!("--follow cannot read compressed input");
+    }
+
+    // A killed writer would leave the current file without its footer
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst))?;
+
+    println!("Following {}...", input);
+    let mut reader = FollowReader::new(input, schema.clone())?;
+    reader.set_engineering_values(args.engineering);
+    if !args.channels.is_empty() {
+        reader.select_columns_by_name(&args.channels)?;
+    }
+    // The input is still growing, so it is not hashed
+    let metadata = file_metadata(schema, vec![describe_source(input, false)?])?;
+    let props = props.set_key_value_metadata(Some(metadata)).build();
+    let roll_after = args.roll_secs.map(Duration::from_secs);
+    let idle_timeout = args.idle_timeout.map(Duration::from_secs);
+
+    let mut part: Option<Part> = None;
+    let mut parts_written = 0;
+    let mut last_rows = Instant::now();
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            println!("Interrupted, stopping");
+            break;
+        }
+
+        // Close the current file once it is full or old enough
+        if let Some(current) = &part {
+            let full = args.roll_rows.is_some_and(|n| current.rows >= n);
+            let old = roll_after.is_some_and(|d| current.opened.elapsed() >= d);
+            if full || old {
+                let current = part.take().expect("checked above");
+                current.writer.close()?;
+                println!("Wrote {} rows to {}", current.rows, current.path);
+            }
+        }
+
+        let room = match (&part, args.roll_rows) {
+            (Some(current), Some(n)) => n - current.rows,
+            (None, Some(n)) => n,
+            (_, None) => batch_size,
+        };
+        match reader.read_new(batch_size.min(room))? {
+            Some(batch) => {
+                last_rows = Instant::now();
+                let current = match &mut part {
+                    Some(current) => current,
+                    None => {
+                        let path = part_path(&args.output, parts_written);
+                        parts_written += 1;
+                        part.insert(Part {
+                            writer: create_writer(&path, arrow_schema, &props)?,
+                            path,
+                            rows: 0,
+                            opened: Instant::now(),
+                        })
+                    }
+                };
+                current.rows += batch.num_rows;
+                current
+                    .writer
+                    .write(&to_record_batch(batch, schema, arrow_schema)?)?;
+            }
+            None => {
+                if idle_timeout.is_some_and(|d| last_rows.elapsed() >= d) {
+                    println!("No new rows for {:?}, stopping", last_rows.elapsed());
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(args.poll_ms));
+            }
+        }
+    }
+
+    if let Some(current) = part {
+        current.writer.close()?;
+        println!("Wrote {} rows to {}", current.rows, current.path);
+    }
+    if reader.remainder() > 0 {
+        eprintln!(
+            "Warning: {} bytes of an incomplete last row were not converted",
+            reader.remainder()
+        );
+    }
+    Ok(())
+}
+
+/// Schema used for headerless input when --schema is not given.
+const DEFAULT_SCHEMA: &str = ".data/schema.json";
+
+fn read_schema(path: &str) -> anyhow::Result<Schema> {
+    println!("Reading schema from {}...", path);
+    let schema_content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&schema_content)?)
+}
+
+/// Reads a container header at the start of `stream`, if there is one.
+fn read_header(stream: &mut impl BufRead) -> anyhow::Result<Option<Header>> {
+    Ok(match stream.fill_buf()?.starts_with(&MAGIC) {
+        true => Some(Header::read_from(stream)?),
+        false => None,
+    })
+}
+
+/// The --start/--end window in the timestamp unit, if either is given.
+fn time_window(args: &Args, schema: &Schema) -> anyhow::Result<Option<(i64, i64)>> {
+    if args.start.is_none() && args.end.is_none() {
+        return Ok(None);
+    }
+    let unit = schema
+        .timestamp
+        .as_ref()
+        .map(|t| t.unit())
+        .ok_or_else(|| anyhow::anyhow!("--start/--end need a schema with a timestamp"))?;
+    let t0 = match &args.start {
+        Some(v) => parse_time(v, unit)?,
+        None => i64::MIN,
+    };
+    let t1 = match &args.end {
+        Some(v) => parse_time(v, unit)?,
+        None => i64::MAX,
+    };
+    Ok(Some((t0, t1)))
+}
+
+/// Parses a --start/--end value into the given timestamp unit.
+fn parse_time(value: &str, unit: TimeUnit) -> anyhow::Result<i64> {
+    if let Ok(raw) = value.parse::<i64>() {
+        return Ok(raw);
+    }
+    let time = chrono::DateTime::parse_from_rfc3339(value)
+        .map_err(|e| anyhow::anyhow!("invalid time '{}': {}", value, e))?;
+    Ok(match unit {
+        TimeUnit::Microsecond => time.timestamp_micros(),
+        TimeUnit::Nanosecond => time
+            .timestamp_nanos_opt()
+            .ok_or_else(|| anyhow::anyhow!("time '{}' is out of range", value))?,
+    })
 }
 
 fn main() -> anyhow::Result<()> {
@@ -35,87 +701,275 @@ fn main() -> anyhow::Result<()> {
 
     let args = Args::parse();
 
-    println!("Reading schema from {}...", args.schema);
-    let schema_content = std::fs::read_to_string(&args.schema)?;
-    let schema: Schema = serde_json::from_str(&schema_content)?;
+    // Stdin is opened up front so that a container header can be read from it
+    let mut stdin = match args.input[0].as_str() {
+        "-" => {
+            let stdin = BufReader::new(std::io::stdin());
+            let (reader, compression) = match args.input_compression.compression() {
+                Some(compression) => (compression.decoder(stdin)?, compression),
+                None => decompress(stdin)?,
+            };
+            Some((BufReader::new(reader), compression))
+        }
+        _ => None,
+    };
 
-    println!("Initializing reader for {}...", args.input);
-    let mut reader = BatchReader::new(&args.input, schema.clone())?;
-    let total_rows = reader.total_rows();
-    println!("Total rows found: {}", total_rows);
+    let (schema, schema_source) = match &args.schema {
+        Some(path) => (read_schema(path)?, path.clone()),
+        None => {
+            let path = &args.input[0];
+            let header = match &mut stdin {
+                Some((stream, _)) => read_header(stream)?,
+                None if !path.contains(['*', '?', '[']) => {
+                    match args.input_compression.compression() {
+                        Some(compression) => {
+                            read_header(&mut BufReader::new(compression.open(path)?))?
+                        }
+                        None => Header::read_file(path)?,
+                    }
+                }
+                None => None,
+            };
+            match header {
+                Some(header) => {
+                    println!("Using the schema embedded in {}", path);
+                    (header.schema, path.clone())
+                }
+                None => (read_schema(DEFAULT_SCHEMA)?, DEFAULT_SCHEMA.to_string()),
+            }
+        }
+    };
+    let problems = schema.validate();
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("Schema problem: {}", problem);
+        }
+        anyhow::bail!("{} has {} schema problem(s)", schema_source, problems.len());
+    }
 
     // Calculate batch size based on memory limit
     // Row size in bytes
     let row_size_bytes = schema.row_size();
     // Target memory usage per batch (let's use 50% of limit for safety buffer)
     let target_batch_mem_bytes = (args.memory_limit_mb * 1024 * 1024) / 2;
-    let batch_size = std::cmp::max(1, target_batch_mem_bytes / row_size_bytes);
+    let batch_size = std::cmp::max(1, target_batch_mem_bytes / row_size_bytes.max(1));
 
     println!(
         "Memory limit: {} MB. Calculated batch size: {} rows.",
         args.memory_limit_mb, batch_size
     );
 
-    // Setup Arrow Schema
-    let mut fields = Vec::new();
-    // Add Timestamp field
-    fields.push(Field::new("timestamp", ArrowType::Float64, false));
+    // Check the writer options before reading (and hashing) any input
+    let columns = output_columns(&schema, &args.channels)?;
+    let arrow_schema = arrow_schema(&schema, &columns, args.engineering);
+    let props = writer_properties(&args, &arrow_schema)?;
 
-    for channel in &schema.channels {
-        let arrow_type = match channel.data_type {
-            DataType::Bit => ArrowType::UInt8,
-            DataType::Int => ArrowType::Int32,
-            DataType::Float => ArrowType::Float64,
-        };
-        fields.push(Field::new(&channel.name, arrow_type, false));
+    if args.follow {
+        return follow(&args, &schema, &arrow_schema, props, batch_size);
     }
-    let arrow_schema = Arc::new(ArrowSchema::new(fields));
 
-    // Setup Parquet Writer
-    let file = File::create(&args.output)?;
-    let props = WriterProperties::builder()
-        .set_compression(Compression::UNCOMPRESSED)
-        .build();
-    let mut writer = ArrowWriter::try_new(file, arrow_schema.clone(), Some(props))?;
+    println!("Initializing reader for {}...", args.input.join(", "));
+    let mode = if args.lenient {
+        ReadMode::Lenient
+    } else {
+        ReadMode::Strict
+    };
+    let time_window = time_window(&args, &schema)?;
+    let path = &args.input[0];
+    let multiple = args.input.len() > 1 || path.contains(['*', '?', '[']);
 
-    let mut processed_rows = 0;
+    let paths = match args.input.len() {
+        1 if multiple => glob_paths(path)?,
+        _ => args.input.clone(),
+    };
 
-    while let Some(channels_data) = reader.read_batch(batch_size) {
-        let current_batch_size = channels_data[0].len();
-        if current_batch_size == 0 {
-            break;
+    // Rows to convert; unbounded when streaming
+    let mut rows = 0..usize::MAX;
+    // Stdin and compressed files are decoded as streams, one file after the
+    // other. A compressed file is only loaded whole when a time range needs
+    // random access.
+    let mut streams = Vec::new();
+    if path == "-" && !multiple {
+        if time_window.is_some() {
+            anyhow::bail!("--start/--end need a file input, not stdin");
         }
+        if let Some((reader, compression)) = stdin.take() {
+            streams.push((
+                path.clone(),
+                Box::new(reader) as Box<dyn Read + Send>,
+                compression,
+            ));
+        }
+    } else if time_window.is_none() {
+        for path in &paths {
+            let (reader, compression) = open_input(path, args.input_compression)
+                .map_err(|e| anyhow::anyhow!("cannot open {}: {}", path, e))?;
+            streams.push((path.clone(), reader, compression));
+        }
+        if streams
+            .iter()
+            .all(|(_, _, compression)| *compression == InputCompression::None)
+        {
+            streams.clear();
+        }
+    }
 
-        // Read timestamps for this batch
-        let timestamps = reader.read_timestamps(processed_rows, current_batch_size);
-
-        // Convert to Arrow Arrays
-        let mut columns: Vec<ArrayRef> = Vec::with_capacity(channels_data.len() + 1);
+    let mut input = if !streams.is_empty() {
+        let mut readers = Vec::with_capacity(streams.len());
+        for (path, stream, compression) in streams {
+            if compression != InputCompression::None {
+                println!("Decompressing {:?} input {}", compression, path);
+            }
+            let mut reader =
+                StreamReader::with_mode(stream, schema.clone(), mode).map_err(|error| {
+                    binary_processor::Error::InFile {
+                        file: path.clone(),
+                        error: Box::new(error),
+                    }
+                })?;
+            reader.set_engineering_values(args.engineering);
+            if !args.channels.is_empty() {
+                reader.select_columns_by_name(&args.channels)?;
+            }
+            readers.push((path, reader));
+        }
+        Input::Stream(Streams {
+            readers,
+            current: 0,
+            previous: None,
+        })
+    } else if multiple {
+        let mut reader = match args.input_compression.compression() {
+            Some(compression) => {
+                MultiFileReader::with_compression(&paths, schema.clone(), mode, compression)?
+            }
+            None => MultiFileReader::with_mode(&paths, schema.clone(), mode)?,
+        };
+        for (path, file) in reader.paths().iter().zip(reader.files()) {
+            println!("  {}: {} rows", path, file.total_rows());
+            if file.remainder() > 0 {
+                eprintln!(
+                    "Warning: skipping {} trailing bytes of {} that do not form a complete row",
+                    file.remainder(),
+                    path
+                );
+            }
+        }
+        // A gap of more than two sample intervals suggests lost rows
+        for boundary in reader.boundaries()? {
+            if boundary.step.is_some_and(|step| boundary.gap() > 2 * step) {
+                eprintln!(
+                    "Warning: timestamps jump by {} at the start of {} (previous interval {})",
+                    boundary.gap(),
+                    reader.paths()[boundary.file],
+                    boundary.step.unwrap_or_default()
+                );
+            }
+        }
+        reader.set_engineering_values(args.engineering);
+        reader.set_threads(args.threads)?;
+        if !args.channels.is_empty() {
+            reader.select_columns_by_name(&args.channels)?;
+        }
+        println!("Total rows found: {}", reader.total_rows());
+        rows = 0..reader.total_rows();
 
-        // Add Timestamp column
-        columns.push(Arc::new(Float64Array::from(timestamps)));
+        if let Some((t0, t1)) = time_window {
+            rows = reader.time_range_rows(t0, t1)?;
+            reader.seek(rows.start)?;
+            println!(
+                "Time range selects rows {}..{} ({} rows)",
+                rows.start,
+                rows.end,
+                rows.len()
+            );
+        }
+        Input::Files(reader)
+    } else {
+        let mut reader = match args.input_compression.compression() {
+            Some(compression) => {
+                BatchReader::with_compression(path, schema.clone(), mode, compression)?
+            }
+            None => BatchReader::with_mode(path, schema.clone(), mode)?,
+        };
+        if reader.compression() != InputCompression::None {
+            println!(
+                "Decompressed {:?} input into memory for random access",
+                reader.compression()
+            );
+        }
+        reader.set_engineering_values(args.engineering);
+        reader.set_threads(args.threads)?;
+        if !args.channels.is_empty() {
+            reader.select_columns_by_name(&args.channels)?;
+        }
+        let total_rows = reader.total_rows();
+        println!("Total rows found: {}", total_rows);
+        if reader.remainder() > 0 {
+            eprintln!(
+                "Warning: skipping {} trailing bytes that do not form a complete row",
+                reader.remainder()
+            );
+        }
+        rows = 0..total_rows;
 
-        for data in channels_data {
-            let array: ArrayRef = match data {
-                ChannelData::Bit(v) => Arc::new(UInt8Array::from(v)),
-                ChannelData::Int(v) => Arc::new(Int32Array::from(v)),
-                ChannelData::Float(v) => Arc::new(Float64Array::from(v)),
-            };
-            columns.push(array);
+        // Restrict to the requested time window
+        if let Some((t0, t1)) = time_window {
+            rows = reader.time_range_rows(t0, t1)?;
+            reader.seek(rows.start)?;
+            println!(
+                "Time range selects rows {}..{} ({} rows)",
+                rows.start,
+                rows.end,
+                rows.len()
+            );
         }
+        Input::File(Box::new(reader))
+    };
 
-        let batch = RecordBatch::try_new(arrow_schema.clone(), columns)?;
-        writer.write(&batch)?;
+    let sources = paths
+        .iter()
+        .map(|p| describe_source(p, true))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let metadata = file_metadata(&schema, sources)?;
+    let props = props.set_key_value_metadata(Some(metadata)).build();
 
-        processed_rows += current_batch_size;
-        println!(
-            "Processed {} / {} rows ({:.1}%)",
-            processed_rows,
-            total_rows,
-            (processed_rows as f64 / total_rows as f64) * 100.0
-        );
+    // Setup Parquet Writer
+    let mut writer = create_writer(&args.output, &arrow_schema, &props)?;
+
+    let mut processed_rows = 0;
+
+    while processed_rows < rows.len() {
+        let current_batch_size = std::cmp::min(batch_size, rows.len() - processed_rows);
+        let batch = match input.read_batch(current_batch_size)? {
+            Some(batch) => batch,
+            None => break,
+        };
+
+        processed_rows += batch.num_rows;
+        writer.write(&to_record_batch(batch, &schema, &arrow_schema)?)?;
+        match &input {
+            Input::File(_) | Input::Files(_) => println!(
+                "Processed {} / {} rows ({:.1}%)",
+                processed_rows,
+                rows.len(),
+                (processed_rows as f64 / rows.len() as f64) * 100.0
+            ),
+            Input::Stream(_) => println!("Processed {} rows", processed_rows),
+        }
     }
 
+    if let Input::Stream(streams) = &input {
+        for (path, reader) in &streams.readers {
+            if reader.remainder() > 0 {
+                eprintln!(
+                    "Warning: skipped {} trailing bytes of {} that do not form a complete row",
+                    reader.remainder(),
+                    path
+                );
+            }
+        }
+    }
     writer.close()?;
     println!("Conversion complete. Output saved to {}", args.output);
     let parsing_duration = parsing_start.elapsed();
@@ -0,0 +1,373 @@
+use arrow::array::{
+    ArrayRef, BooleanArray, Float64Array, Int16Array, RecordBatch, TimestampMillisecondArray,
+};
+use arrow::compute::{cast, concat_batches};
+use arrow::datatypes::{DataType as ArrowType, Field, Schema as ArrowSchema, TimeUnit};
+use binary_processor::{
+    BatchReader, BitField, Channel, ChannelData, DataType, Endianness, RowWriter, Schema,
+    Timestamp, TimestampEncoding,
+};
+use chrono::{TimeZone, Utc};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+
+const ROWS: usize = 2000;
+
+/// Scratch directory for one test, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("round_trip_{}_{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn path(&self, file: &str) -> String {
+        self.0.join(file).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(binary: &str, args: &[&str]) {
+    let output = Command::new(binary).args(args).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{} {:?} failed:\n{}{}",
+        binary,
+        args,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Packed bits, a padding gap, a channel in the other byte order and
+/// trailing bytes after the last field.
+fn schema(byte_order: Endianness, timestamp: Timestamp) -> Schema {
+    let mut status = Channel::new("status", DataType::U16);
+    status.bits = [("ready", 0), ("fault", 7), ("overrun", 12)]
+        .into_iter()
+        .map(|(name, position)| BitField {
+            name: name.to_string(),
+            position,
+        })
+        .collect();
+    let mut pressure = Channel::new("pressure", DataType::I16);
+    pressure.scale = Some(0.01);
+    pressure.unit = Some("bar".to_string());
+    let mut counter = Channel::new("counter", DataType::U32);
+    counter.byte_order = Some(match byte_order {
+        Endianness::Little => Endianness::Big,
+        Endianness::Big => Endianness::Little,
+    });
+    Schema {
+        channels: vec![
+            status,
+            Channel::new("reserved", DataType::Padding(2)),
+            pressure,
+            Channel::new("temperature", DataType::F32),
+            counter,
+            Channel::new("energy", DataType::Float),
+            Channel::new("mode", DataType::Bit),
+        ],
+        timestamp: Some(timestamp),
+        byte_order,
+        row_size: Some(40),
+    }
+}
+
+/// Rows of `schema` with timestamps `step` apart from `start`.
+fn write_rows(path: &str, schema: &Schema, start: i64, step: i64) {
+    let timestamps: Vec<i64> = (0..ROWS as i64).map(|i| start + i * step).collect();
+    let rows = 0..ROWS;
+    let columns = vec![
+        ChannelData::Bool(rows.clone().map(|i| i % 2 == 0).collect()),
+        ChannelData::Bool(rows.clone().map(|i| i % 7 == 0).collect()),
+        ChannelData::Bool(rows.clone().map(|i| i % 3 == 1).collect()),
+        ChannelData::I16(rows.clone().map(|i| i as i16 - 1000).collect()),
+        ChannelData::F32(rows.clone().map(|i| i as f32 * 0.5).collect()),
+        ChannelData::U32(rows.clone().map(|i| 0xA0B0_C0D0 + i as u32).collect()),
+        ChannelData::Float(rows.clone().map(|i| i as f64 / 7.0).collect()),
+        ChannelData::Bit(rows.map(|i| (i % 4) as u8).collect()),
+    ];
+    let file = std::fs::File::create(path).unwrap();
+    let mut writer = RowWriter::new(file, schema.clone()).unwrap();
+    writer.write_columns(&timestamps, &columns).unwrap();
+    writer.into_inner().unwrap();
+}
+
+/// Converts `schema` rows to Parquet and back, expecting the same bytes and
+/// schema again.
+fn assert_round_trip(name: &str, schema: Schema, start: i64, step: i64) {
+    let dir = TempDir::new(name);
+    let (schema_path, data, parquet) = (
+        dir.path("schema.json"),
+        dir.path("data.bin"),
+        dir.path("data.parquet"),
+    );
+    let (replay_schema, replay) = (dir.path("replay.json"), dir.path("replay.bin"));
+    std::fs::write(&schema_path, serde_json::to_string(&schema).unwrap()).unwrap();
+    write_rows(&data, &schema, start, step);
+
+    run(
+        env!("CARGO_BIN_EXE_data_converter"),
+        &["-i", &data, "-s", &schema_path, "-o", &parquet],
+    );
+    run(
+        env!("CARGO_BIN_EXE_parquet_to_binary"),
+        &["-i", &parquet, "-o", &replay, "-s", &replay_schema],
+    );
+
+    let restored: Schema =
+        serde_json::from_str(&std::fs::read_to_string(&replay_schema).unwrap()).unwrap();
+    assert_eq!(restored, schema);
+    let replayed = std::fs::read(&replay).unwrap();
+    assert_eq!(replayed.len(), ROWS * schema.row_size());
+    assert!(
+        replayed == std::fs::read(&data).unwrap(),
+        "replayed rows differ from the input"
+    );
+}
+
+#[test]
+fn little_endian_nanosecond_rows_round_trip() {
+    let timestamp = Timestamp {
+        encoding: TimestampEncoding::I64Nanos,
+        epoch: None,
+        timezone: Some("UTC".to_string()),
+    };
+    // 1 ms apart from 2024-03-01
+    let start = 1_709_294_400_000_000_000;
+    assert_round_trip(
+        "little",
+        schema(Endianness::Little, timestamp),
+        start,
+        1_000_000,
+    );
+}
+
+#[test]
+fn big_endian_tick_rows_round_trip() {
+    // 100 Hz ticks counted from a custom epoch, i.e. 10 ms per tick
+    let timestamp = Timestamp {
+        encoding: TimestampEncoding::U32Ticks { tick_rate: 100.0 },
+        epoch: Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()),
+        timezone: None,
+    };
+    let start = timestamp.epoch.unwrap().timestamp_nanos_opt().unwrap();
+    assert_round_trip("big", schema(Endianness::Big, timestamp), start, 10_000_000);
+}
+
+#[test]
+fn engineering_values_replay_without_scaling() {
+    let dir = TempDir::new("engineering");
+    let (schema_path, data, parquet) = (
+        dir.path("schema.json"),
+        dir.path("data.bin"),
+        dir.path("data.parquet"),
+    );
+    let (replay_schema, replay) = (dir.path("replay.json"), dir.path("replay.bin"));
+    // A scaled channel that keeps its type when written as engineering values
+    let mut energy = Channel::new("energy", DataType::Float);
+    energy.scale = Some(2.0);
+    energy.offset = Some(1.0);
+    let schema = Schema {
+        channels: vec![energy],
+        timestamp: Some(Timestamp {
+            encoding: TimestampEncoding::I64Nanos,
+            epoch: None,
+            timezone: None,
+        }),
+        byte_order: Endianness::Little,
+        row_size: None,
+    };
+    std::fs::write(&schema_path, serde_json::to_string(&schema).unwrap()).unwrap();
+    let raw: Vec<f64> = (0..ROWS).map(|i| i as f64 / 4.0).collect();
+    let timestamps: Vec<i64> = (0..ROWS as i64).map(|i| i * 1_000_000).collect();
+    let mut writer = RowWriter::new(std::fs::File::create(&data).unwrap(), schema.clone()).unwrap();
+    writer
+        .write_columns(&timestamps, &[ChannelData::Float(raw.clone())])
+        .unwrap();
+    writer.into_inner().unwrap();
+
+    run(
+        env!("CARGO_BIN_EXE_data_converter"),
+        &[
+            "-i",
+            &data,
+            "-s",
+            &schema_path,
+            "-o",
+            &parquet,
+            "--engineering",
+        ],
+    );
+    run(
+        env!("CARGO_BIN_EXE_parquet_to_binary"),
+        &["-i", &parquet, "-o", &replay, "-s", &replay_schema],
+    );
+
+    let restored: Schema =
+        serde_json::from_str(&std::fs::read_to_string(&replay_schema).unwrap()).unwrap();
+    assert_eq!(restored.channels[0].scaling(), None);
+    let batch = BatchReader::new(&replay, restored)
+        .unwrap()
+        .read_batch(ROWS)
+        .unwrap()
+        .unwrap();
+    let scaled: Vec<f64> = raw.iter().map(|v| v * 2.0 + 1.0).collect();
+    assert_eq!(batch.timestamps, timestamps);
+    assert_eq!(batch.columns, vec![ChannelData::Float(scaled)]);
+}
+
+#[test]
+fn rows_with_gzip_magic_convert_as_uncompressed() {
+    let dir = TempDir::new("magic");
+    let (schema_path, data, parquet) = (
+        dir.path("schema.json"),
+        dir.path("data.bin"),
+        dir.path("data.parquet"),
+    );
+    let (replay_schema, replay) = (dir.path("replay.json"), dir.path("replay.bin"));
+    let schema = Schema {
+        channels: ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|name| Channel::new(name, DataType::Bit))
+            .collect(),
+        timestamp: None,
+        byte_order: Endianness::Little,
+        row_size: None,
+    };
+    std::fs::write(&schema_path, serde_json::to_string(&schema).unwrap()).unwrap();
+    // Every row starts with the gzip ID bytes and deflate method
+    std::fs::write(&data, [0x1f, 0x8b, 0x08, 0x00].repeat(ROWS)).unwrap();
+
+    run(
+        env!("CARGO_BIN_EXE_data_converter"),
+        &[
+            "-i",
+            &data,
+            "-s",
+            &schema_path,
+            "-o",
+            &parquet,
+            "--input-compression",
+            "none",
+        ],
+    );
+    run(
+        env!("CARGO_BIN_EXE_parquet_to_binary"),
+        &["-i", &parquet, "-o", &replay, "-s", &replay_schema],
+    );
+    assert!(std::fs::read(&replay).unwrap() == std::fs::read(&data).unwrap());
+}
+
+fn read_parquet(path: &str) -> RecordBatch {
+    let builder =
+        ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap()).unwrap();
+    let schema = builder.schema().clone();
+    let batches: Vec<RecordBatch> = builder.build().unwrap().map(Result::unwrap).collect();
+    concat_batches(&schema, &batches).unwrap()
+}
+
+/// A Parquet file from another tool has no stored schema, so the row layout
+/// is reconstructed from its columns: booleans are packed into bits and the
+/// millisecond timestamps are widened to microseconds.
+#[test]
+fn foreign_parquet_round_trips_through_rows() {
+    let dir = TempDir::new("foreign");
+    let (parquet, replay_schema, replay, again) = (
+        dir.path("foreign.parquet"),
+        dir.path("replay.json"),
+        dir.path("replay.bin"),
+        dir.path("again.parquet"),
+    );
+    let rows = 0..ROWS as i64;
+    let flag = |n: i64| -> ArrayRef {
+        Arc::new(BooleanArray::from_iter(
+            rows.clone().map(|i| Some(i % n == 0)),
+        ))
+    };
+    let timestamp = ArrowType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()));
+    let schema = Arc::new(ArrowSchema::new(vec![
+        Field::new("timestamp", timestamp, false),
+        Field::new("ok", ArrowType::Boolean, false),
+        Field::new("alarm", ArrowType::Boolean, false),
+        Field::new("level", ArrowType::Int16, false),
+        Field::new("armed", ArrowType::Boolean, false),
+        Field::new("rate", ArrowType::Float64, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(
+            TimestampMillisecondArray::from_iter_values(
+                rows.clone().map(|i| 1_709_294_400_000 + i * 10),
+            )
+            .with_timezone("UTC"),
+        ),
+        flag(2),
+        flag(7),
+        Arc::new(Int16Array::from_iter_values(
+            rows.clone().map(|i| i as i16 - 1000),
+        )),
+        flag(3),
+        Arc::new(Float64Array::from_iter_values(
+            rows.clone().map(|i| i as f64 / 7.0),
+        )),
+    ];
+    let original = RecordBatch::try_new(schema.clone(), columns).unwrap();
+    let mut writer =
+        ArrowWriter::try_new(std::fs::File::create(&parquet).unwrap(), schema, None).unwrap();
+    writer.write(&original).unwrap();
+    writer.close().unwrap();
+
+    run(
+        env!("CARGO_BIN_EXE_parquet_to_binary"),
+        &["-i", &parquet, "-o", &replay, "-s", &replay_schema],
+    );
+    let restored: Schema =
+        serde_json::from_str(&std::fs::read_to_string(&replay_schema).unwrap()).unwrap();
+    assert_eq!(
+        restored.timestamp.map(|t| t.encoding),
+        Some(TimestampEncoding::I64Micros)
+    );
+    let bits: Vec<Vec<&str>> = restored
+        .channels
+        .iter()
+        .map(|c| c.bits.iter().map(|b| b.name.as_str()).collect())
+        .collect();
+    assert_eq!(bits, [vec!["ok", "alarm"], vec![], vec!["armed"], vec![]]);
+    run(
+        env!("CARGO_BIN_EXE_data_converter"),
+        &["-i", &replay, "-s", &replay_schema, "-o", &again],
+    );
+
+    let converted = read_parquet(&again);
+    let names = |schema: &ArrowSchema| -> Vec<String> {
+        schema.fields().iter().map(|f| f.name().clone()).collect()
+    };
+    assert_eq!(names(&converted.schema()), names(&original.schema()));
+    for (index, column) in original.columns().iter().enumerate() {
+        let expected = match index {
+            0 => cast(
+                column,
+                &ArrowType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            )
+            .unwrap(),
+            _ => column.clone(),
+        };
+        assert_eq!(
+            converted.column(index).as_ref(),
+            expected.as_ref(),
+            "column {}",
+            original.schema().field(index).name()
+        );
+    }
+}
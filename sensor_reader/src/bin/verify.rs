@@ -1,4 +1,4 @@
-use sensor_reader::{get_sensor_data, SensorData};
+use sensor_reader::{get_recording_info, get_sensor_data, get_sensor_metadata, SensorData};
 use std::env;
 use std::time::Instant;
 
@@ -11,6 +11,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let file_path = &args[1];
 
     println!("Verifying file: {}", file_path);
+    let info = get_recording_info(file_path)?;
+    if let Some(version) = &info.converter_version {
+        println!("Written by data_converter {}", version);
+    }
+    for source in &info.sources {
+        match (source.size, &source.sha256) {
+            (Some(size), Some(sha256)) => {
+                println!(
+                    "Source: {} ({} bytes, sha256 {})",
+                    source.name, size, sha256
+                )
+            }
+            _ => println!("Source: {}", source.name),
+        }
+    }
 
     let sensors_to_check = vec!["ch_0", "ch_999"];
 
@@ -31,9 +46,52 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("  Type: Float, Count: {}", v.len());
                     println!("  First 5: {:?}", &v[0..5.min(v.len())]);
                 }
+                SensorData::I8(v) => {
+                    println!("  Type: I8, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::I16(v) => {
+                    println!("  Type: I16, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::I64(v) => {
+                    println!("  Type: I64, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::U16(v) => {
+                    println!("  Type: U16, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::U32(v) => {
+                    println!("  Type: U32, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::U64(v) => {
+                    println!("  Type: U64, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::F32(v) => {
+                    println!("  Type: F32, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::Bool(v) => {
+                    println!("  Type: Bool, Count: {}", v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
+                SensorData::Timestamp {
+                    values: v, unit, ..
+                } => {
+                    println!("  Type: Timestamp ({:?}), Count: {}", unit, v.len());
+                    println!("  First 5: {:?}", &v[0..5.min(v.len())]);
+                }
             },
             Err(e) => println!("  Error reading sensor: {}", e),
         }
+        if let Ok(metadata) = get_sensor_metadata(file_path, sensor) {
+            if let Some(unit) = metadata.unit {
+                println!("  Unit: {}", unit);
+            }
+        }
         let reading_duration = reading_start.elapsed();
         println!("  Reading duration: {} ms", reading_duration.as_millis());
     }
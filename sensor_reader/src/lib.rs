@@ -1,3 +1,10 @@
+use arrow::array::{ArrayRef, AsArray};
+pub use arrow::datatypes::TimeUnit;
+use arrow::datatypes::{
+    ArrowPrimitiveType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+    TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+    TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
 use parquet::file::reader::{FileReader, SerializedFileReader};
 use std::fs::File;
 use std::path::Path;
@@ -6,6 +13,155 @@ pub enum SensorData {
     Bit(Vec<u8>),
     Int(Vec<i32>),
     Float(Vec<f64>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I64(Vec<i64>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F32(Vec<f32>),
+    Bool(Vec<bool>),
+    /// Times since the Unix epoch in `unit`, e.g. the `timestamp` column.
+    Timestamp {
+        values: Vec<i64>,
+        unit: TimeUnit,
+        timezone: Option<String>,
+    },
+}
+
+impl SensorData {
+    fn extend(&mut self, other: SensorData) -> Result<(), Box<dyn std::error::Error>> {
+        match (self, other) {
+            (SensorData::Bit(v), SensorData::Bit(o)) => v.extend(o),
+            (SensorData::Int(v), SensorData::Int(o)) => v.extend(o),
+            (SensorData::Float(v), SensorData::Float(o)) => v.extend(o),
+            (SensorData::I8(v), SensorData::I8(o)) => v.extend(o),
+            (SensorData::I16(v), SensorData::I16(o)) => v.extend(o),
+            (SensorData::I64(v), SensorData::I64(o)) => v.extend(o),
+            (SensorData::U16(v), SensorData::U16(o)) => v.extend(o),
+            (SensorData::U32(v), SensorData::U32(o)) => v.extend(o),
+            (SensorData::U64(v), SensorData::U64(o)) => v.extend(o),
+            (SensorData::F32(v), SensorData::F32(o)) => v.extend(o),
+            (SensorData::Bool(v), SensorData::Bool(o)) => v.extend(o),
+            (
+                SensorData::Timestamp {
+                    values,
+                    unit,
+                    timezone,
+                },
+                SensorData::Timestamp {
+                    values: o,
+                    unit: other_unit,
+                    timezone: other_timezone,
+                },
+            ) if *unit == other_unit && *timezone == other_timezone => values.extend(o),
+            _ => return Err("Type mismatch between batches".into()),
+        }
+        Ok(())
+    }
+}
+
+/// Engineering metadata stored by `data_converter` on a sensor column.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SensorMetadata {
+    pub unit: Option<String>,
+    pub description: Option<String>,
+    /// Present when the column holds raw values: `physical = raw * scale + offset`.
+    pub scale: Option<f64>,
+    pub offset: Option<f64>,
+}
+
+pub fn get_sensor_metadata<P: AsRef<Path>>(
+    file_path: P,
+    sensor_name: &str,
+) -> Result<SensorMetadata, Box<dyn std::error::Error>> {
+    let file = File::open(file_path.as_ref())?;
+    let builder = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(file)?;
+    let arrow_schema = builder.schema();
+    let field = arrow_schema
+        .field_with_name(sensor_name)
+        .map_err(|_| format!("Sensor '{}' not found in file", sensor_name))?;
+
+    let metadata = field.metadata();
+    let number = |key: &str| -> Result<Option<f64>, Box<dyn std::error::Error>> {
+        match metadata.get(key) {
+            Some(v) => Ok(Some(v.parse()?)),
+            None => Ok(None),
+        }
+    };
+    Ok(SensorMetadata {
+        unit: metadata.get("unit").cloned(),
+        description: metadata.get("description").cloned(),
+        scale: number("scale")?,
+        offset: number("offset")?,
+    })
+}
+
+/// An input file a Parquet file was converted from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceFile {
+    pub name: String,
+    /// Size in bytes, absent for stdin and followed files.
+    pub size: Option<u64>,
+    /// Lowercase hex SHA-256 of the file contents, absent with `size`.
+    pub sha256: Option<String>,
+}
+
+/// Key-value metadata holding the `schema.json` the rows were decoded with.
+pub const SCHEMA_KEY: &str = "data_converter.schema";
+/// Key-value metadata holding the JSON list of input files.
+pub const SOURCES_KEY: &str = "data_converter.sources";
+/// Key-value metadata holding the version of `data_converter`.
+pub const VERSION_KEY: &str = "data_converter.version";
+
+/// Provenance stored by `data_converter` in the Parquet key-value metadata.
+/// Files written by other tools (or older versions) leave the fields empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingInfo {
+    /// The `schema.json` the rows were decoded with, as JSON text.
+    pub schema: Option<String>,
+    pub sources: Vec<SourceFile>,
+    /// Version of `data_converter` that wrote the file.
+    pub converter_version: Option<String>,
+}
+
+pub fn get_recording_info<P: AsRef<Path>>(
+    file_path: P,
+) -> Result<RecordingInfo, Box<dyn std::error::Error>> {
+    let file = File::open(file_path.as_ref())?;
+    let reader = SerializedFileReader::new(file)?;
+    let lookup = |key: &str| {
+        reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .and_then(|kv| kv.iter().find(|entry| entry.key == key))
+            .and_then(|entry| entry.value.clone())
+    };
+
+    let mut sources = Vec::new();
+    if let Some(json) = lookup(SOURCES_KEY) {
+        let list: serde_json::Value = serde_json::from_str(&json)?;
+        for source in list.as_array().ok_or("Source list is not an array")? {
+            sources.push(SourceFile {
+                name: source["name"]
+                    .as_str()
+                    .ok_or("Source file without a name")?
+                    .to_string(),
+                size: source["size"].as_u64(),
+                sha256: source["sha256"].as_str().map(str::to_string),
+            });
+        }
+    }
+    Ok(RecordingInfo {
+        schema: lookup(SCHEMA_KEY),
+        sources,
+        converter_version: lookup(VERSION_KEY),
+    })
+}
+
+fn values<T: ArrowPrimitiveType>(array: &ArrayRef) -> Vec<T::Native> {
+    array.as_primitive::<T>().values().to_vec()
 }
 
 pub fn get_sensor_data<P: AsRef<Path>>(
@@ -51,52 +207,43 @@ pub fn get_sensor_data<P: AsRef<Path>>(
     // Build reader with projection
     // We can use the mask to select columns by their root index
     let mask = parquet::arrow::ProjectionMask::roots(builder.parquet_schema(), vec![column_idx]);
-    let mut reader = builder.with_projection(mask).build()?;
+    let reader = builder.with_projection(mask).build()?;
 
     let mut result_data: Option<SensorData> = None;
 
-    while let Some(batch_result) = reader.next() {
+    for batch_result in reader {
         let batch = batch_result?;
         let array = batch.column(0); // We only projected one column
 
-        match array.data_type() {
-            arrow::datatypes::DataType::UInt8 => {
-                let values = array
-                    .as_any()
-                    .downcast_ref::<arrow::array::UInt8Array>()
-                    .unwrap();
-                let vec: Vec<u8> = values.values().to_vec();
-                match &mut result_data {
-                    Some(SensorData::Bit(v)) => v.extend(vec),
-                    None => result_data = Some(SensorData::Bit(vec)),
-                    _ => return Err("Type mismatch between batches".into()),
-                }
-            }
-            arrow::datatypes::DataType::Int32 => {
-                let values = array
-                    .as_any()
-                    .downcast_ref::<arrow::array::Int32Array>()
-                    .unwrap();
-                let vec: Vec<i32> = values.values().to_vec();
-                match &mut result_data {
-                    Some(SensorData::Int(v)) => v.extend(vec),
-                    None => result_data = Some(SensorData::Int(vec)),
-                    _ => return Err("Type mismatch between batches".into()),
-                }
-            }
-            arrow::datatypes::DataType::Float64 => {
-                let values = array
-                    .as_any()
-                    .downcast_ref::<arrow::array::Float64Array>()
-                    .unwrap();
-                let vec: Vec<f64> = values.values().to_vec();
-                match &mut result_data {
-                    Some(SensorData::Float(v)) => v.extend(vec),
-                    None => result_data = Some(SensorData::Float(vec)),
-                    _ => return Err("Type mismatch between batches".into()),
-                }
+        let data = match array.data_type() {
+            arrow::datatypes::DataType::UInt8 => SensorData::Bit(values::<UInt8Type>(array)),
+            arrow::datatypes::DataType::Int32 => SensorData::Int(values::<Int32Type>(array)),
+            arrow::datatypes::DataType::Float64 => SensorData::Float(values::<Float64Type>(array)),
+            arrow::datatypes::DataType::Int8 => SensorData::I8(values::<Int8Type>(array)),
+            arrow::datatypes::DataType::Int16 => SensorData::I16(values::<Int16Type>(array)),
+            arrow::datatypes::DataType::Int64 => SensorData::I64(values::<Int64Type>(array)),
+            arrow::datatypes::DataType::UInt16 => SensorData::U16(values::<UInt16Type>(array)),
+            arrow::datatypes::DataType::UInt32 => SensorData::U32(values::<UInt32Type>(array)),
+            arrow::datatypes::DataType::UInt64 => SensorData::U64(values::<UInt64Type>(array)),
+            arrow::datatypes::DataType::Float32 => SensorData::F32(values::<Float32Type>(array)),
+            arrow::datatypes::DataType::Boolean => {
+                SensorData::Bool(array.as_boolean().values().iter().collect())
             }
+            arrow::datatypes::DataType::Timestamp(unit, timezone) => SensorData::Timestamp {
+                values: match unit {
+                    TimeUnit::Second => values::<TimestampSecondType>(array),
+                    TimeUnit::Millisecond => values::<TimestampMillisecondType>(array),
+                    TimeUnit::Microsecond => values::<TimestampMicrosecondType>(array),
+                    TimeUnit::Nanosecond => values::<TimestampNanosecondType>(array),
+                },
+                unit: *unit,
+                timezone: timezone.as_deref().map(str::to_string),
+            },
             dt => return Err(format!("Unsupported data type: {:?}", dt).into()),
+        };
+        match &mut result_data {
+            Some(existing) => existing.extend(data)?,
+            None => result_data = Some(data),
         }
     }
 